@@ -0,0 +1,218 @@
+// Todd Fleming 2023
+
+//! The `#[generator]` attribute and `gen!` macro. Use them through
+//! `gen::generator` and `gen::gen`, with gen's `macros` feature
+//! enabled.
+
+use proc_macro::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+
+/// Turn a function into one returning a generator.
+///
+/// The function's return type is the item type. Its body yields items
+/// with `yield_!(item)`, and returns `()`. The function instead returns
+/// `impl Iterator<Item = T>`, built with `gen::generate`.
+///
+/// Arguments are moved into the generator. If it borrows arguments,
+/// name their lifetimes; `use<..>` lists the function's generic
+/// parameters when it has lifetimes, and elided ones can't be listed.
+#[proc_macro_attribute]
+pub fn generator(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if let Some(tt) = attr.into_iter().next() {
+        return error(tt.span(), "#[generator] takes no arguments");
+    }
+    match expand(item.into_iter().collect()) {
+        Ok(stream) => stream,
+        Err((span, message)) => error(span, message),
+    }
+}
+
+/// An inline generator: `gen! { yield 1; for x in xs { yield x; } }`
+///
+/// This expands to `gen::generate(|co| async move { .. })`, with each
+/// `yield expr` becoming `co.yield_(expr).await`.
+#[proc_macro]
+pub fn gen(input: TokenStream) -> TokenStream {
+    let mut closure = parse("move |__co| async move");
+    closure.extend([group(Delimiter::Brace, rewrite_yields(input))]);
+    let mut call = parse("::gen::generate");
+    call.extend([group(Delimiter::Parenthesis, closure)]);
+    call
+}
+
+// Replace each `yield expr` with `__co.yield_(expr).await`. The
+// expression runs to the next `;`, the next `,` outside a turbofish
+// (as in a match arm), or the end of its group.
+fn rewrite_yields(input: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut tokens = input.into_iter().peekable();
+    while let Some(tt) = tokens.next() {
+        match tt {
+            TokenTree::Ident(ident) if ident.to_string() == "yield" => {
+                let mut expr: Vec<TokenTree> = Vec::new();
+                let mut turbofish = 0;
+                while let Some(tt) =
+                    tokens.next_if(|tt| !is_punct(tt, ';') && (turbofish > 0 || !is_punct(tt, ',')))
+                {
+                    if is_punct(&tt, '<')
+                        && (turbofish > 0 || expr.last().is_some_and(|tt| is_punct(tt, ':')))
+                    {
+                        turbofish += 1;
+                    } else if is_punct(&tt, '>') && turbofish > 0 {
+                        turbofish -= 1;
+                    }
+                    expr.push(tt);
+                }
+                let mut item = group(
+                    Delimiter::Parenthesis,
+                    rewrite_yields(TokenStream::from_iter(expr)),
+                );
+                item.set_span(ident.span());
+                out.extend(parse("__co.yield_"));
+                out.push(item);
+                out.extend(parse(".await"));
+            }
+            TokenTree::Group(g) => {
+                let mut rewritten = Group::new(g.delimiter(), rewrite_yields(g.stream()));
+                rewritten.set_span(g.span());
+                out.push(TokenTree::Group(rewritten));
+            }
+            tt => out.push(tt),
+        }
+    }
+    TokenStream::from_iter(out)
+}
+
+fn expand(tokens: Vec<TokenTree>) -> Result<TokenStream, (Span, &'static str)> {
+    let fn_pos = tokens
+        .iter()
+        .position(|tt| is_ident(tt, "fn"))
+        .ok_or((Span::call_site(), "#[generator] applies to functions"))?;
+    let Some(TokenTree::Group(body)) = tokens.last() else {
+        return Err((Span::call_site(), "#[generator] needs a function body"));
+    };
+    if body.delimiter() != Delimiter::Brace {
+        return Err((body.span(), "#[generator] needs a function body"));
+    }
+
+    // fn name <generics> (args)
+    let mut pos = fn_pos + 2;
+    let mut params = Vec::new();
+    let mut has_lifetimes = false;
+    if tokens.get(pos).is_some_and(|tt| is_punct(tt, '<')) {
+        let end = generics_end(&tokens, pos);
+        for param in tokens[pos + 1..end].split(|tt| is_punct(tt, ',')) {
+            match param {
+                [TokenTree::Punct(p), name, ..] if p.as_char() == '\'' => {
+                    has_lifetimes = true;
+                    params.push(TokenTree::Punct(p.clone()));
+                    params.push(name.clone());
+                }
+                [kw, name, ..] if is_ident(kw, "const") => params.push(name.clone()),
+                [name, ..] => params.push(name.clone()),
+                [] => continue,
+            }
+            params.push(punct(','));
+        }
+        pos = end + 1;
+    }
+    pos += 1;
+
+    // -> Item [where ..] { body }
+    let arrow = tokens.get(pos).is_some_and(|tt| is_punct(tt, '-'))
+        && tokens.get(pos + 1).is_some_and(|tt| is_punct(tt, '>'));
+    if !arrow {
+        return Err((
+            tokens[fn_pos + 1].span(),
+            "#[generator] functions declare their item type as the return type",
+        ));
+    }
+    let item_start = pos + 2;
+    let item_end = tokens[item_start..tokens.len() - 1]
+        .iter()
+        .position(|tt| is_ident(tt, "where"))
+        .map_or(tokens.len() - 1, |i| item_start + i);
+
+    let mut out = TokenStream::from_iter(tokens[..pos].iter().cloned());
+    out.extend(parse("-> impl ::core::iter::Iterator<Item ="));
+    out.extend(tokens[item_start..item_end].iter().cloned());
+    out.extend(parse(">"));
+    if has_lifetimes {
+        out.extend(parse("+ use<"));
+        out.extend(params);
+        out.extend(parse(">"));
+    }
+    out.extend(tokens[item_end..tokens.len() - 1].iter().cloned());
+
+    let mut async_body = parse(
+        "#[allow(unused_macros)]
+        macro_rules! yield_ {
+            ($item:expr) => {
+                __co.yield_($item).await
+            };
+        }",
+    );
+    async_body.extend([TokenTree::Group(body.clone())]);
+    let mut closure = parse("move |__co| async move");
+    closure.extend([group(Delimiter::Brace, async_body)]);
+    let mut call = parse("::gen::generate::<");
+    call.extend(tokens[item_start..item_end].iter().cloned());
+    call.extend(parse(", _, _>"));
+    call.extend([group(Delimiter::Parenthesis, closure)]);
+    out.extend([group(Delimiter::Brace, call)]);
+    Ok(out)
+}
+
+// Index of the `>` closing generics which open at `start`
+fn generics_end(tokens: &[TokenTree], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, tt) in tokens.iter().enumerate().skip(start) {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+            // Not the `>` of `->`
+            TokenTree::Punct(p)
+                if p.as_char() == '>' && !(i > 0 && is_joint_punct(&tokens[i - 1], '-')) =>
+            {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => (),
+        }
+    }
+    tokens.len()
+}
+
+fn is_ident(tt: &TokenTree, name: &str) -> bool {
+    matches!(tt, TokenTree::Ident(ident) if ident.to_string() == name)
+}
+
+fn is_punct(tt: &TokenTree, ch: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == ch)
+}
+
+fn is_joint_punct(tt: &TokenTree, ch: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == ch && p.spacing() == Spacing::Joint)
+}
+
+fn punct(ch: char) -> TokenTree {
+    TokenTree::Punct(proc_macro::Punct::new(ch, Spacing::Alone))
+}
+
+fn parse(source: &str) -> TokenStream {
+    source.parse().unwrap()
+}
+
+fn group(delimiter: Delimiter, stream: TokenStream) -> TokenTree {
+    TokenTree::Group(Group::new(delimiter, stream))
+}
+
+fn error(span: Span, message: &str) -> TokenStream {
+    parse(&format!("::core::compile_error!({message:?});"))
+        .into_iter()
+        .map(|mut tt| {
+            tt.set_span(span);
+            tt
+        })
+        .collect()
+}
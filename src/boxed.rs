@@ -0,0 +1,100 @@
+// Generators whose future type is erased.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// A [Generator] whose future type is erased, so it can be named in
+/// struct fields and trait methods
+///
+/// `Output` is the async function's return type. [Generator::boxed]
+/// makes one.
+///
+/// ```
+/// use gen::{generate, BoxGenerator};
+///
+/// # #[cfg(any(feature = "std", feature = "spin"))] {
+/// struct Countdown {
+///     numbers: BoxGenerator<'static, u32>,
+/// }
+///
+/// impl Countdown {
+///     fn new(from: u32) -> Self {
+///         let numbers = generate(move |co| async move {
+///             for i in (0..=from).rev() {
+///                 co.yield_(i).await;
+///             }
+///         });
+///         Countdown {
+///             numbers: numbers.boxed(),
+///         }
+///     }
+/// }
+///
+/// let countdown = Countdown::new(3);
+/// assert_eq!(countdown.numbers.collect::<Vec<_>>(), [3, 2, 1, 0]);
+/// # }
+/// ```
+pub type BoxGenerator<'a, Item, Output = (), Resume = (), S = SyncShared<Item, Resume>> =
+    Generator<Item, Pin<Box<dyn Future<Output = Output> + Send + 'a>>, Resume, S>;
+
+/// [BoxGenerator] for futures which aren't `Send`
+///
+/// [Generator::boxed_local] makes one.
+pub type LocalBoxGenerator<'a, Item, Output = (), Resume = (), S = SyncShared<Item, Resume>> =
+    Generator<Item, Pin<Box<dyn Future<Output = Output> + 'a>>, Resume, S>;
+
+impl<'a, Item, F: Future + 'a, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Pin<Box<F>>, Resume, S>
+{
+    /// Erase the future's type. The generator keeps its state and
+    /// settings.
+    pub fn boxed(self) -> BoxGenerator<'a, Item, F::Output, Resume, S>
+    where
+        F: Send,
+    {
+        self.map_future(
+            |future| future as Pin<Box<dyn Future<Output = _> + Send>>,
+            |value| value,
+        )
+    }
+
+    /// Erase the type of a future which isn't `Send`
+    pub fn boxed_local(self) -> LocalBoxGenerator<'a, Item, F::Output, Resume, S> {
+        self.map_future(
+            |future| future as Pin<Box<dyn Future<Output = _>>>,
+            |value| value,
+        )
+    }
+}
+
+/// Like [generate](crate::generate), but checks up front that the generator can move
+/// to another thread, and erases its future's type.
+///
+/// Passing a future which holds something `!Send` across an `await`
+/// fails here, at the call, instead of wherever the generator is
+/// later sent. The result can be stored in `'static` job queues.
+///
+/// ```
+/// use gen::{generate_send, BoxGenerator};
+/// use std::{collections::VecDeque, thread};
+///
+/// let mut jobs: VecDeque<BoxGenerator<'static, u32>> = VecDeque::new();
+/// jobs.push_back(generate_send(|co| async move {
+///     co.yield_all([1, 2, 3]).await;
+/// }));
+///
+/// let job = jobs.pop_front().unwrap();
+/// let sum = thread::spawn(move || job.sum::<u32>()).join().unwrap();
+/// assert_eq!(sum, 6);
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_send<Item, F, Fut>(f: F) -> BoxGenerator<'static, Item, Fut::Output>
+where
+    Item: Send + 'static,
+    F: FnOnce(crate::Communication<Item>) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    crate::generate(f).boxed()
+}
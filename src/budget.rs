@@ -0,0 +1,132 @@
+// Bounding the work done by a single resume.
+
+use crate::{shared::SharedState, Communication, Generator, GeneratorState};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Result of [Generator::next_with_budget]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Budgeted<Item> {
+    /// The async function yielded an item.
+    Item(Item),
+
+    /// The async function needs more polls, or is waiting on a future
+    /// which hasn't woken it yet. Call again later.
+    Exhausted,
+
+    /// The async function has finished.
+    Done,
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Like [Iterator::next], but polls the async function at most
+    /// `max_polls` times. This bounds how long one call takes when the
+    /// async function awaits futures besides `yield_`, e.g. in a game
+    /// loop which resumes it once per frame.
+    ///
+    /// This doesn't use the generator's [crate::Driver].
+    ///
+    /// ```
+    /// use gen::{generate, Budgeted};
+    /// use std::{future::poll_fn, task::Poll};
+    ///
+    /// let mut generator = generate(|co| async move {
+    ///     // Takes 3 polls
+    ///     let mut polls = 0;
+    ///     poll_fn(|cx| {
+    ///         polls += 1;
+    ///         if polls == 3 {
+    ///             return Poll::Ready(());
+    ///         }
+    ///         cx.waker().wake_by_ref();
+    ///         Poll::Pending
+    ///     })
+    ///     .await;
+    ///     co.yield_("loaded").await;
+    /// });
+    ///
+    /// assert_eq!(generator.next_with_budget(2), Budgeted::Exhausted);
+    /// assert_eq!(generator.next_with_budget(2), Budgeted::Item("loaded"));
+    /// assert_eq!(generator.next_with_budget(2), Budgeted::Done);
+    /// ```
+    pub fn next_with_budget(&mut self, max_polls: usize) -> Budgeted<Item> {
+        let mut resume = Some(Resume::default());
+        for _ in 0..max_polls {
+            if self.done {
+                return Budgeted::Done;
+            }
+            let waker = self.sync_waker();
+            match self.poll_resume(resume.take(), &mut Context::from_waker(&waker)) {
+                Poll::Ready(Ok(GeneratorState::Yielded(item))) => return Budgeted::Item(item),
+                Poll::Ready(Ok(GeneratorState::Complete(value))) => {
+                    self.return_value = Some(value);
+                    return Budgeted::Done;
+                }
+                Poll::Ready(Err(error)) => {
+                    crate::error::misuse::<()>(error);
+                    return Budgeted::Done;
+                }
+                Poll::Pending => {
+                    if !self.woken() {
+                        break;
+                    }
+                }
+            }
+        }
+        match self.done {
+            true => Budgeted::Done,
+            false => Budgeted::Exhausted,
+        }
+    }
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Suspend the async function once without yielding an item.
+    ///
+    /// This is a checkpoint for long stretches of work: it ends a poll
+    /// so [Generator::next_with_budget] and [crate::scheduler] can
+    /// move on. [Iterator::next] just polls again.
+    ///
+    /// ```
+    /// use gen::{generate, Budgeted};
+    ///
+    /// let mut generator = generate(|co| async move {
+    ///     let mut sum = 0u64;
+    ///     for i in 0..3_000 {
+    ///         sum += i;
+    ///         if i % 1_000 == 999 {
+    ///             co.yield_now().await;
+    ///         }
+    ///     }
+    ///     co.yield_(sum).await;
+    /// });
+    ///
+    /// assert_eq!(generator.next_with_budget(2), Budgeted::Exhausted);
+    /// assert_eq!(generator.next_with_budget(2), Budgeted::Item(4_498_500));
+    /// ```
+    pub fn yield_now(&self) -> YieldNow {
+        YieldNow(false)
+    }
+}
+
+/// Future returned by [Communication::yield_now]
+#[derive(Debug)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -0,0 +1,56 @@
+// Generators which run ahead of the consumer.
+
+use crate::{shared::SharedState, Communication, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Version of [crate::generate] whose async function may run up to
+/// `n` items ahead of the consumer.
+///
+/// `yield_` queues its item and only suspends once `n` items are
+/// queued. [Iterator::next] drains the queue before running the
+/// async function again, so bursts of items cost one resume instead
+/// of one each.
+///
+/// ```
+/// use gen::generate_buffered;
+/// use std::cell::Cell;
+///
+/// let produced = &Cell::new(0);
+/// let mut iter = generate_buffered(4, |co| async move {
+///     for i in 0..8 {
+///         produced.set(i + 1);
+///         co.yield_(i).await;
+///     }
+/// });
+///
+/// assert_eq!(iter.next(), Some(0));
+/// assert_eq!(produced.get(), 4);
+/// assert_eq!(iter.next(), Some(1));
+/// assert_eq!(produced.get(), 4);
+/// assert_eq!(iter.collect::<Vec<_>>(), [2, 3, 4, 5, 6, 7]);
+/// ```
+pub fn generate_buffered<Item, F, Fut>(n: usize, f: F) -> Generator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let mut generator = crate::generate(f);
+    generator.set_buffer(n);
+    generator
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Let the async function queue up to `n` items before `yield_`
+    /// suspends; see [generate_buffered]. `0` acts like `1`, the
+    /// default.
+    ///
+    /// A buffered `yield_` resolves to `Resume::default()`; values
+    /// passed to [Generator::send] are dropped.
+    pub fn set_buffer(&mut self, n: usize) {
+        self.shared
+            .with(|slot| slot.buffer = (n > 1).then_some((n, Resume::default as fn() -> _)));
+    }
+}
@@ -0,0 +1,111 @@
+// Configuring a generator before it starts.
+
+use crate::{shared::SharedState, CancellationToken, Communication, Driver, DropPolicy, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Settings for a new [Generator]
+///
+/// Each option matches a `Generator` setter or `generate_*`
+/// function; [GenBuilder::spawn] applies them to a new generator.
+///
+/// ```
+/// use gen::{DropPolicy, GenBuilder};
+///
+/// let mut iter = GenBuilder::new()
+///     .buffer(8)
+///     .size_hint((3, Some(3)))
+///     .drop_policy(DropPolicy::Close)
+///     .spawn(|co| async move {
+///         co.yield_all(["a", "b", "c"]).await;
+///     });
+///
+/// assert_eq!(iter.size_hint(), (3, Some(3)));
+/// assert_eq!(iter.collect::<Vec<_>>(), ["a", "b", "c"]);
+/// ```
+#[derive(Default)]
+pub struct GenBuilder {
+    buffer: usize,
+    size_hint: Option<(usize, Option<usize>)>,
+    drop_policy: DropPolicy,
+    driver: Option<Box<dyn Driver + Send + Sync>>,
+    token: Option<CancellationToken>,
+    #[cfg(feature = "std")]
+    catch_panics: bool,
+}
+
+impl GenBuilder {
+    /// Default settings, the same as [crate::generate]'s
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [Generator::set_buffer].
+    pub fn buffer(mut self, n: usize) -> Self {
+        self.buffer = n;
+        self
+    }
+
+    /// See [crate::generate_with_hint].
+    pub fn size_hint(mut self, hint: (usize, Option<usize>)) -> Self {
+        self.size_hint = Some(hint);
+        self
+    }
+
+    /// See [Generator::set_drop_policy].
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.drop_policy = policy;
+        self
+    }
+
+    /// See [Generator::set_driver].
+    pub fn driver(mut self, driver: impl Driver + Send + Sync + 'static) -> Self {
+        self.driver = Some(Box::new(driver));
+        self
+    }
+
+    /// See [Generator::set_cancellation_token].
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// See [Generator::set_catch_panics].
+    #[cfg(feature = "std")]
+    pub fn catch_panics(mut self, catch_panics: bool) -> Self {
+        self.catch_panics = catch_panics;
+        self
+    }
+
+    /// Make the generator, like [crate::generate]. It starts on the
+    /// first resume.
+    pub fn spawn<Item, F, Fut>(self, f: F) -> Generator<Item, Pin<Box<Fut>>>
+    where
+        F: FnOnce(Communication<Item>) -> Fut,
+        Fut: Future,
+    {
+        self.spawn_bidi(f)
+    }
+
+    /// Make a bidirectional generator, like [crate::generate_bidi].
+    pub fn spawn_bidi<Item, Resume, F, Fut>(self, f: F) -> Generator<Item, Pin<Box<Fut>>, Resume>
+    where
+        Resume: Default,
+        F: FnOnce(Communication<Item, Resume>) -> Fut,
+        Fut: Future,
+    {
+        let mut generator = crate::generate_bidi(f);
+        generator.set_buffer(self.buffer);
+        if let Some(hint) = self.size_hint {
+            generator.shared.with(|slot| slot.remaining = hint);
+        }
+        generator.set_drop_policy(self.drop_policy);
+        generator.driver = self.driver;
+        if let Some(token) = self.token {
+            generator.set_cancellation_token(token);
+        }
+        #[cfg(feature = "std")]
+        generator.set_catch_panics(self.catch_panics);
+        generator
+    }
+}
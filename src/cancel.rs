@@ -0,0 +1,224 @@
+// Cancelling the async function from outside.
+
+use crate::{
+    shared::{self, Lock, SharedState},
+    Communication, Generator,
+};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+/// Asks async functions to stop
+///
+/// Clones share the same state, so one can be handed to whoever
+/// decides when to stop. See [Generator::set_cancellation_token].
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    // Each waker with the number of pending `Cancelled` futures using it
+    wakers: Lock<Vec<(Waker, usize)>>,
+}
+
+impl CancellationToken {
+    /// A token which hasn't been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel, waking everything awaiting [Communication::cancelled].
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        let wakers = core::mem::take(&mut *shared::lock(&self.0.wakers));
+        for (waker, _) in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Whether [CancellationToken::cancel] was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    // Wake `waker` on cancel
+    fn register(&self, waker: &Waker) {
+        let mut wakers = shared::lock(&self.0.wakers);
+        match wakers.iter_mut().find(|(w, _)| w.will_wake(waker)) {
+            Some((_, users)) => *users += 1,
+            None => wakers.push((waker.clone(), 1)),
+        }
+    }
+
+    // Undo one `register(waker)`
+    fn unregister(&self, waker: &Waker) {
+        let mut wakers = shared::lock(&self.0.wakers);
+        if let Some(i) = wakers.iter().position(|(w, _)| w.will_wake(waker)) {
+            wakers[i].1 -= 1;
+            if wakers[i].1 == 0 {
+                wakers.swap_remove(i);
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Run `f` if the async function is dropped before it completes,
+    /// e.g. because the generator was dropped mid-iteration. See
+    /// [Communication::on_cancel].
+    pub fn on_cancel(&mut self, f: impl FnOnce() + Send + 'static) {
+        self.shared.with(|slot| slot.on_cancel.push(Box::new(f)));
+    }
+
+    /// Let `token` cancel the async function; see
+    /// [Communication::cancelled].
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.shared.with(|slot| slot.token = Some(token));
+    }
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Whether the generator's [CancellationToken] was cancelled, or
+    /// [Generator::close] was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0
+            .with(|slot| slot.closed || slot.token.as_ref().is_some_and(|t| t.is_cancelled()))
+    }
+
+    /// Run `f` if the async function is dropped before it completes.
+    ///
+    /// This signals early termination even where nothing captured
+    /// would notice being dropped. Callbacks run in the order they
+    /// were registered, when the generator drops or
+    /// [Generator::close] drops the async function.
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::sync::mpsc;
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// let iter = generate(|co| async move {
+    ///     co.on_cancel(move || tx.send("session aborted").unwrap());
+    ///     for i in 0.. {
+    ///         co.yield_(i).await;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(iter.take(2).count(), 2);
+    /// assert_eq!(rx.try_recv(), Ok("session aborted"));
+    /// ```
+    pub fn on_cancel(&self, f: impl FnOnce() + Send + 'static) {
+        self.0.with(|slot| slot.on_cancel.push(Box::new(f)));
+    }
+
+    /// Resolves once [Communication::is_cancelled] is `true`. Race
+    /// work against it, e.g. with a `select!` macro, to stop cleanly.
+    ///
+    /// ```
+    /// use gen::{generate, CancellationToken};
+    /// use std::{future::poll_fn, pin::pin, future::Future, task::Poll};
+    ///
+    /// let token = CancellationToken::new();
+    /// let mut ticks = generate(|co| async move {
+    ///     let mut work = pin!(async {
+    ///         for i in 0.. {
+    ///             co.yield_(i).await;
+    ///         }
+    ///     });
+    ///     let mut cancelled = pin!(co.cancelled());
+    ///     // A minimal select!
+    ///     poll_fn(|cx| {
+    ///         if cancelled.as_mut().poll(cx).is_ready() {
+    ///             return Poll::Ready("cancelled");
+    ///         }
+    ///         work.as_mut().poll(cx).map(|()| "finished")
+    ///     })
+    ///     .await
+    /// });
+    /// ticks.set_cancellation_token(token.clone());
+    ///
+    /// assert_eq!(ticks.next(), Some(0));
+    /// assert_eq!(ticks.next(), Some(1));
+    /// token.cancel();
+    /// assert_eq!(ticks.next(), None);
+    /// assert_eq!(ticks.return_value(), Some(&"cancelled"));
+    /// ```
+    pub fn cancelled(&self) -> Cancelled<'_, Item, Resume, S> {
+        Cancelled {
+            co: self,
+            registered: None,
+        }
+    }
+}
+
+/// Future returned by [Communication::cancelled]
+pub struct Cancelled<'a, Item, Resume, S> {
+    co: &'a Communication<Item, Resume, S>,
+    // The token and waker from the last pending poll, unregistered
+    // when replaced or dropped so the token's wakers stay bounded
+    registered: Option<(CancellationToken, Waker)>,
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Future for Cancelled<'_, Item, Resume, S> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.co.is_cancelled() {
+            self.unregister();
+            return Poll::Ready(());
+        }
+        let token = self.co.0.with(|slot| slot.token.clone());
+        let current = match (&self.registered, &token) {
+            (Some((old, waker)), Some(token)) => {
+                Arc::ptr_eq(&old.0, &token.0) && waker.will_wake(cx.waker())
+            }
+            _ => false,
+        };
+        if !current {
+            self.unregister();
+            if let Some(token) = token {
+                token.register(cx.waker());
+                self.registered = Some((token, cx.waker().clone()));
+            }
+        }
+        // It may have been cancelled before registering
+        if self
+            .registered
+            .as_ref()
+            .is_some_and(|(t, _)| t.is_cancelled())
+        {
+            self.unregister();
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl<Item, Resume, S> Cancelled<'_, Item, Resume, S> {
+    fn unregister(&mut self) {
+        if let Some((token, waker)) = self.registered.take() {
+            token.unregister(&waker);
+        }
+    }
+}
+
+impl<Item, Resume, S> Drop for Cancelled<'_, Item, Resume, S> {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
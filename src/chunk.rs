@@ -0,0 +1,60 @@
+// Yielding several items per suspension.
+
+use crate::{shared::SharedState, Communication};
+use core::{future::poll_fn, task::Poll};
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Pass all of `items` to [Generator](crate::Generator) with a
+    /// single suspension.
+    ///
+    /// The async function resumes once the consumer has taken the
+    /// last of them; [Iterator::next] hands out the rest without
+    /// running it. The values `next` and `send` pass back for them
+    /// are discarded. After [Generator::close](crate::Generator::close),
+    /// this resolves right away, dropping the untaken items.
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::cell::Cell;
+    ///
+    /// let blocks_read = &Cell::new(0);
+    /// let mut samples = generate(|co| async move {
+    ///     for block in [[1, 2, 3], [4, 5, 6]] {
+    ///         blocks_read.set(blocks_read.get() + 1);
+    ///         co.yield_chunk(block).await;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(samples.by_ref().take(3).sum::<i32>(), 6);
+    /// assert_eq!(blocks_read.get(), 1);
+    /// assert_eq!(samples.polls(), 1);
+    /// ```
+    pub async fn yield_chunk(&self, items: impl IntoIterator<Item = Item>) {
+        let last = self.0.with(|slot| {
+            let mut last = None;
+            for item in items {
+                last = Some(slot.push(item));
+            }
+            let last = last?;
+            slot.chunk_end = last + 1;
+            Some(last)
+        });
+        let Some(last) = last else {
+            return;
+        };
+        poll_fn(|_| {
+            self.0.with(|slot| {
+                let under_capacity = slot
+                    .buffer
+                    .is_some_and(|(capacity, _)| slot.items.len() < capacity);
+                if slot.closed || under_capacity || last < slot.delivered {
+                    slot.resume = None;
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,89 @@
+// Running helpers written for other item types.
+
+use crate::{shared::SharedState, Communication, Generator, GeneratorState};
+use core::{future::Future, pin::pin};
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Run a helper which yields `Inner`s, passing each through `map`
+    /// to [Generator]. Returns the helper's return value.
+    ///
+    /// `f` receives a `Communication<Inner, Resume>` for the helper.
+    /// Resume values pass back to it unchanged, and it sees
+    /// [Generator::close] through [Communication::is_closed]. Nothing
+    /// is buffered; each item is mapped as the helper yields it.
+    ///
+    /// ```
+    /// use gen::{generate, Communication};
+    ///
+    /// struct RawEvent(u8);
+    /// #[derive(Debug, PartialEq)]
+    /// enum Event {
+    ///     Key(char),
+    /// }
+    ///
+    /// async fn read_raw(co: &Communication<RawEvent>, bytes: &[u8]) -> usize {
+    ///     for &b in bytes {
+    ///         co.yield_(RawEvent(b)).await;
+    ///     }
+    ///     bytes.len()
+    /// }
+    ///
+    /// let mut events = generate(|co| async move {
+    ///     let decode = |RawEvent(b)| Event::Key(b as char);
+    ///     co.map_yield(decode, |co| async move { read_raw(&co, b"hi").await })
+    ///         .await
+    /// });
+    ///
+    /// assert_eq!(events.by_ref().collect::<Vec<_>>(), [Event::Key('h'), Event::Key('i')]);
+    /// assert_eq!(events.return_value(), Some(&2));
+    /// ```
+    pub async fn map_yield<Inner, M, F, Fut>(&self, mut map: M, f: F) -> Fut::Output
+    where
+        M: FnMut(Inner) -> Item,
+        F: FnOnce(Communication<Inner, Resume>) -> Fut,
+        Fut: Future,
+    {
+        let shared = crate::SyncShared::<Inner, Resume>::default();
+        let future = pin!(f(Communication::new(shared.clone())));
+        let mut inner = Generator::new(shared, Some(future));
+        let mut resume = None;
+        loop {
+            if self.is_closed() {
+                inner.shared.with(|slot| slot.closed = true);
+            }
+            let state = core::future::poll_fn(|cx| inner.poll_resume(resume.take(), cx)).await;
+            match state
+                .map_or_else(crate::error::misuse, Some)
+                .expect("map_yield's helper panicked")
+            {
+                GeneratorState::Yielded(item) => resume = Some(self.yield_(map(item)).await),
+                GeneratorState::Complete(value) => return value,
+            }
+        }
+    }
+
+    /// [Communication::map_yield] for helpers whose items convert
+    /// [Into] `Item`.
+    ///
+    /// ```
+    /// use gen::{generate, Communication};
+    ///
+    /// async fn small(co: Communication<u8>) {
+    ///     co.yield_all([1, 2]).await;
+    /// }
+    ///
+    /// let iter = generate(|co: Communication<u64>| async move {
+    ///     co.with_converter(small).await;
+    ///     co.yield_(u64::MAX).await;
+    /// });
+    /// assert_eq!(iter.collect::<Vec<_>>(), [1, 2, u64::MAX]);
+    /// ```
+    pub async fn with_converter<U, F, Fut>(&self, f: F) -> Fut::Output
+    where
+        U: Into<Item>,
+        F: FnOnce(Communication<U, Resume>) -> Fut,
+        Fut: Future,
+    {
+        self.map_yield(U::into, f).await
+    }
+}
@@ -0,0 +1,45 @@
+// Debug output for the public types. None of it needs `Item: Debug`.
+
+use crate::{shared::SharedState, Communication, Generator, YieldFuture};
+use core::{fmt, future::Future};
+
+impl<Item, Fut: Future, Resume, S: SharedState<Item, Resume>> fmt::Debug
+    for Generator<Item, Fut, Resume, S>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (delivered, queued) = self.shared.with(|slot| (slot.delivered, slot.items.len()));
+        let mut debug = f.debug_struct("Generator");
+        debug
+            .field("started", &self.started)
+            .field("done", &self.done)
+            .field("poisoned", &self.poisoned)
+            .field("items_so_far", &delivered)
+            .field("queued", &queued)
+            .field("polls", &self.polls)
+            .field("has_driver", &self.driver.is_some());
+        #[cfg(feature = "std")]
+        debug.field("catch_panics", &self.catch_panics);
+        debug.finish_non_exhaustive()
+    }
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> fmt::Debug for Communication<Item, Resume, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (closed, queued) = self.0.with(|slot| (slot.closed, slot.items.len()));
+        f.debug_struct("Communication")
+            .field("closed", &closed)
+            .field("queued", &queued)
+            .finish()
+    }
+}
+
+impl<Item, Resume, S> fmt::Debug for YieldFuture<'_, Item, Resume, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("YieldFuture");
+        match self.value {
+            Some(_) => debug.field("yielded", &false),
+            None => debug.field("yielded", &true).field("ticket", &self.ticket),
+        };
+        debug.finish()
+    }
+}
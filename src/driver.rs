@@ -0,0 +1,78 @@
+// Driving futures other than yield_.
+
+use crate::{Communication, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Drives an async function while it awaits futures other than
+/// [Communication::yield_]
+///
+/// The generator hands the driver a future which finishes once the
+/// async function yields or returns. The driver polls it to completion,
+/// blocking as needed. That's the shape of `block_on` in pollster,
+/// futures-executor, and most reactors, so a closure which forwards to
+/// one is a `Driver`.
+pub trait Driver {
+    /// Poll `future` to completion.
+    fn block_on(&mut self, future: Pin<&mut dyn Future<Output = ()>>);
+}
+
+impl<F: FnMut(Pin<&mut dyn Future<Output = ()>>)> Driver for F {
+    fn block_on(&mut self, future: Pin<&mut dyn Future<Output = ()>>) {
+        self(future)
+    }
+}
+
+/// Version of [crate::generate] whose async function may await any
+/// future. `driver` drives those futures; see [Driver].
+///
+/// ```
+/// use gen::generate_with_driver;
+/// use std::{
+///     future::Future,
+///     pin::Pin,
+///     task::{Context, Waker},
+///     thread,
+///     time::Duration,
+/// };
+///
+/// // A driver which polls until the future finishes. Real drivers
+/// // sleep until woken instead.
+/// let driver = |mut future: Pin<&mut dyn Future<Output = ()>>| {
+///     let mut cx = Context::from_waker(Waker::noop());
+///     while future.as_mut().poll(&mut cx).is_pending() {
+///         thread::yield_now();
+///     }
+/// };
+///
+/// let iter = generate_with_driver(driver, |co| async move {
+///     for i in 0..3 {
+///         // Stands in for a timer or I/O future. It's pending
+///         // without waking anything, so the plain `generate`
+///         // couldn't drive it.
+///         let (tx, rx) = std::sync::mpsc::channel();
+///         thread::spawn(move || {
+///             thread::sleep(Duration::from_millis(1));
+///             tx.send(i).unwrap();
+///         });
+///         let value = std::future::poll_fn(|_| match rx.try_recv() {
+///             Ok(value) => std::task::Poll::Ready(value),
+///             Err(_) => std::task::Poll::Pending,
+///         })
+///         .await;
+///         co.yield_(value).await;
+///     }
+/// });
+///
+/// assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2]);
+/// ```
+pub fn generate_with_driver<Item, D, F, Fut>(driver: D, f: F) -> Generator<Item, Pin<Box<Fut>>>
+where
+    D: Driver + Send + Sync + 'static,
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let mut generator = crate::generate(f);
+    generator.set_driver(driver);
+    generator
+}
@@ -0,0 +1,100 @@
+// Reporting misuse as values instead of panics.
+
+use crate::{shared::SharedState, Generator, GeneratorState};
+use core::{fmt, future::Future};
+
+/// Why [Generator::try_next] or [Generator::try_resume_with] couldn't
+/// resume the async function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// The async function awaited a future other than
+    /// [Communication::yield_](crate::Communication::yield_), and that
+    /// future didn't wake it. Without a [Driver](crate::Driver) there's
+    /// no way to wait for it. The generator is intact; calling again
+    /// polls again.
+    Stalled,
+
+    /// The async function panicked during this call, and the panic
+    /// was caught; see [Generator::set_catch_panics].
+    Panicked,
+
+    /// The async function panicked during an earlier call.
+    Poisoned,
+
+    /// A [YieldFuture](crate::YieldFuture) was polled with no resume
+    /// value waiting, e.g. after being moved to another generator or
+    /// executor. The generator finishes poisoned.
+    WrongExecutor,
+
+    /// The generator was resumed after its async function returned or
+    /// was closed, and there's no return value left to hand back.
+    Closed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::Stalled => "generator's async function awaited a future which didn't wake it",
+            Error::Panicked => "generator's async function panicked",
+            Error::Poisoned => "generator resumed after its async function panicked",
+            Error::WrongExecutor => "YieldFuture used within incorrect executor",
+            Error::Closed => "generator resumed after completion",
+        })
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Like [Iterator::next], but misuse which `next` would panic over
+    /// comes back as an error.
+    ///
+    /// A panic in the async function itself still unwinds unless
+    /// [Generator::set_catch_panics] is on.
+    ///
+    /// ```
+    /// use gen::{generate, Error};
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     co.yield_(1).await;
+    ///     // Pending forever, without waking anything
+    ///     std::future::pending::<()>().await;
+    /// });
+    ///
+    /// assert_eq!(iter.try_next(), Ok(Some(1)));
+    /// assert_eq!(iter.try_next(), Err(Error::Stalled));
+    /// ```
+    pub fn try_next(&mut self) -> Result<Option<Item>, Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+        if self.done {
+            return Ok(None);
+        }
+        match self.try_resume_inner(Resume::default())? {
+            GeneratorState::Yielded(item) => Ok(Some(item)),
+            GeneratorState::Complete(value) => {
+                self.return_value = Some(value);
+                Ok(None)
+            }
+        }
+    }
+}
+
+// Panic over an error, for callers which don't return errors. A
+// caught panic is `None`.
+pub(crate) fn misuse<T>(error: Error) -> Option<T> {
+    match error {
+        Error::Stalled => panic!(
+            "Generator's async function awaited a future other than \
+             Communication::yield_, and that future didn't wake it. \
+             Without a Driver, Iterator::next can only drive such futures \
+             if they wake while being polled."
+        ),
+        Error::Panicked => None,
+        error => panic!("{error}"),
+    }
+}
@@ -0,0 +1,124 @@
+// Generators whose async function can fail.
+
+use crate::{shared::SharedState, Communication, Generator, SyncShared};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Turn a fallible async function into an [Iterator] of `Result`s.
+///
+/// The async function returns `Result<R, E>`, so it may use `?`.
+/// Yielded items arrive as `Ok(item)`. If the async function returns
+/// `Err(e)`, that arrives as a final `Err(e)` item.
+///
+/// ```
+/// use gen::try_generate;
+/// use std::num::ParseIntError;
+///
+/// let iter = try_generate(|co| async move {
+///     for s in ["1", "2", "x", "4"] {
+///         co.yield_(s.parse::<i32>()?).await;
+///     }
+///     Ok::<_, ParseIntError>(())
+/// });
+///
+/// let items: Vec<_> = iter.collect();
+/// assert_eq!(items[..2], [Ok(1), Ok(2)]);
+/// assert!(items[2].is_err());
+/// assert_eq!(items.len(), 3);
+/// ```
+pub fn try_generate<Item, R, E, F, Fut>(f: F) -> TryGenerator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+{
+    TryGenerator(crate::generate(f))
+}
+
+/// An iterator which produces `Ok` items yielded by an async function,
+/// followed by the `Err` it returned, if any.
+///
+/// [try_generate] returns this.
+pub struct TryGenerator<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>>(
+    Generator<Item, Fut, Resume, S>,
+);
+
+impl<Item, Fut: Future, Resume, S> TryGenerator<Item, Fut, Resume, S> {
+    /// The underlying [Generator]. Once the `Err` item has been
+    /// produced, its return value is `None`.
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.0
+    }
+
+    /// Consume this, returning the underlying [Generator].
+    pub fn into_generator(self) -> Generator<Item, Fut, Resume, S> {
+        self.0
+    }
+}
+
+impl<Item, R, E, Fut, Resume, S> Iterator for TryGenerator<Item, Fut, Resume, S>
+where
+    Fut: Future<Output = Result<R, E>> + Unpin,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+    type Item = Result<Item, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.0.next() {
+            return Some(Ok(item));
+        }
+        match self.0.return_value.take()? {
+            Ok(value) => {
+                self.0.return_value = Some(Ok(value));
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+// After the `Err` item, the return value is gone, so it's not
+// produced again
+impl<Item, R, E, Fut, Resume, S> core::iter::FusedIterator for TryGenerator<Item, Fut, Resume, S>
+where
+    Fut: Future<Output = Result<R, E>> + Unpin,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+}
+
+/// For async functions which return `Result`. Items stay plain `Item`s;
+/// the result is available once iteration finishes.
+///
+/// ```
+/// use gen::generate;
+///
+/// let mut iter = generate(|co| async move {
+///     co.yield_(1).await;
+///     co.yield_(2).await;
+///     Err::<(), _>("disk full")
+/// });
+///
+/// assert_eq!(iter.by_ref().sum::<i32>(), 3);
+/// assert_eq!(iter.error(), Some(&"disk full"));
+/// ```
+impl<Item, R, E, Fut, Resume, S> Generator<Item, Fut, Resume, S>
+where
+    Fut: Future<Output = Result<R, E>> + Unpin,
+    S: SharedState<Item, Resume>,
+{
+    /// The error returned by the async function, if it has finished
+    /// with one.
+    pub fn error<'a>(&'a self) -> Option<&'a E>
+    where
+        R: 'a,
+    {
+        self.return_value()?.as_ref().err()
+    }
+
+    /// Consume the generator, returning the result of the async
+    /// function, if it has finished.
+    pub fn into_result(self) -> Option<Result<R, E>> {
+        self.into_return()
+    }
+}
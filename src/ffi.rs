@@ -0,0 +1,142 @@
+//! Driving generators from C.
+//!
+//! A Rust crate which embeds generators exports a function that makes
+//! a [GenHandle]; C then drives it with these functions:
+//!
+//! ```c
+//! typedef struct GenHandle GenHandle;
+//! typedef struct { uint8_t *data; size_t len; } GenBytes;
+//!
+//! bool gen_next(GenHandle *handle, GenBytes *out);
+//! void gen_bytes_free(GenBytes bytes);
+//! void gen_close(GenHandle *handle);
+//! void gen_free(GenHandle *handle);
+//! ```
+//!
+//! A panic in the async function aborts the process, since it can't
+//! unwind into C.
+//!
+//! ```
+//! use gen::{ffi::GenHandle, generate};
+//!
+//! #[no_mangle]
+//! pub extern "C" fn make_greetings() -> *mut GenHandle {
+//!     GenHandle::new(generate(|co| async move {
+//!         co.yield_(b"hello".to_vec()).await;
+//!         co.yield_(b"world".to_vec()).await;
+//!     }))
+//! }
+//! # use gen::ffi::{gen_bytes_free, gen_free, gen_next, GenBytes};
+//! # let handle = make_greetings();
+//! # let mut bytes = GenBytes { data: std::ptr::null_mut(), len: 0 };
+//! # unsafe {
+//! #     assert!(gen_next(handle, &mut bytes));
+//! #     assert_eq!(std::slice::from_raw_parts(bytes.data, bytes.len), b"hello");
+//! #     gen_bytes_free(bytes);
+//! #     gen_free(handle);
+//! # }
+//! ```
+
+use crate::{shared::SharedState, Generator};
+use alloc::{boxed::Box, vec::Vec};
+use core::{future::Future, ptr};
+
+/// A generator of byte buffers, owned by C
+///
+/// C sees this as an opaque type and frees it with [gen_free].
+pub struct GenHandle(Box<dyn Source>);
+
+// What the handle needs from a generator, with its types erased
+trait Source {
+    fn next(&mut self) -> Option<Vec<u8>>;
+    fn close(&mut self);
+}
+
+impl<Fut: Future + Unpin, Resume: Default, S: SharedState<Vec<u8>, Resume>> Source
+    for Generator<Vec<u8>, Fut, Resume, S>
+{
+    fn next(&mut self) -> Option<Vec<u8>> {
+        Iterator::next(self)
+    }
+
+    fn close(&mut self) {
+        Generator::close(self);
+    }
+}
+
+impl GenHandle {
+    /// Move `generator` to the heap for C to drive. The async
+    /// function's return value is discarded.
+    pub fn new<Fut, Resume, S>(generator: Generator<Vec<u8>, Fut, Resume, S>) -> *mut GenHandle
+    where
+        Fut: Future + Unpin + 'static,
+        Resume: Default + 'static,
+        S: SharedState<Vec<u8>, Resume> + 'static,
+    {
+        Box::into_raw(Box::new(GenHandle(Box::new(generator))))
+    }
+}
+
+/// A byte buffer passed to C
+///
+/// Free it with [gen_bytes_free].
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GenBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Resume the generator. Returns `true` and stores the item in `*out`
+/// if it yielded one, or `false` once it has finished.
+///
+/// # Safety
+///
+/// `handle` must come from [GenHandle::new] and not have been freed.
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn gen_next(handle: *mut GenHandle, out: *mut GenBytes) -> bool {
+    let Some(item) = (*handle).0.next() else {
+        return false;
+    };
+    let len = item.len();
+    let data = Box::into_raw(item.into_boxed_slice()) as *mut u8;
+    out.write(GenBytes { data, len });
+    true
+}
+
+/// Free a buffer from [gen_next].
+///
+/// # Safety
+///
+/// `bytes` must come from [gen_next] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gen_bytes_free(bytes: GenBytes) {
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+        bytes.data, bytes.len,
+    )));
+}
+
+/// [Generator::close] the generator. [gen_next] returns `false`
+/// afterwards.
+///
+/// # Safety
+///
+/// `handle` must come from [GenHandle::new] and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gen_close(handle: *mut GenHandle) {
+    (*handle).0.close();
+}
+
+/// Drop the generator. Null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or come from [GenHandle::new], and not have
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gen_free(handle: *mut GenHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
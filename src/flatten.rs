@@ -0,0 +1,118 @@
+// Generators of generators.
+
+use crate::{shared::SharedState, Communication, Generator};
+use core::future::Future;
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+where
+    Item: IntoIterator,
+{
+    /// Like [Iterator::flatten], but keeps access to this generator
+    /// and the inner iterator in progress.
+    ///
+    /// Only one inner iterator runs at a time. It stays available from
+    /// [Flatten::inner] after it finishes, until the next one starts,
+    /// so an inner [Generator]'s return value can be read there.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut rows = generate(|co| async move {
+    ///     co.yield_(vec![1, 2]).await;
+    ///     co.yield_(vec![]).await;
+    ///     co.yield_(vec![3]).await;
+    ///     "3 rows"
+    /// })
+    /// .flatten();
+    ///
+    /// assert_eq!(rows.by_ref().collect::<Vec<_>>(), [1, 2, 3]);
+    /// assert_eq!(rows.outer().return_value(), Some(&"3 rows"));
+    /// ```
+    pub fn flatten(self) -> Flatten<Self, Item::IntoIter> {
+        Flatten {
+            outer: self,
+            inner: None,
+            inner_done: false,
+        }
+    }
+}
+
+/// Items of the iterators a [Generator] yields
+///
+/// [Generator::flatten] returns this.
+pub struct Flatten<G, I> {
+    outer: G,
+    inner: Option<I>,
+    inner_done: bool,
+}
+
+impl<G, I> Flatten<G, I> {
+    /// The generator yielding the inner iterators
+    pub fn outer(&self) -> &G {
+        &self.outer
+    }
+
+    /// The current, or most recently finished, inner iterator
+    pub fn inner(&self) -> Option<&I> {
+        self.inner.as_ref()
+    }
+}
+
+impl<G, I> Iterator for Flatten<G, I>
+where
+    G: Iterator,
+    G::Item: IntoIterator<IntoIter = I>,
+    I: Iterator<Item = <G::Item as IntoIterator>::Item>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let (Some(inner), false) = (&mut self.inner, self.inner_done) {
+                match inner.next() {
+                    Some(item) => return Some(item),
+                    None => self.inner_done = true,
+                }
+            }
+            self.inner = Some(self.outer.next()?.into_iter());
+            self.inner_done = false;
+        }
+    }
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Pass each item of `inner` to [Generator], returning the value
+    /// `inner` returned.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     let inner = generate(|co| async move {
+    ///         co.yield_all(["a", "b"]).await;
+    ///         2
+    ///     });
+    ///     let count = co.yield_flat(inner).await;
+    ///     co.yield_("c").await;
+    ///     count
+    /// });
+    ///
+    /// assert_eq!(iter.by_ref().collect::<Vec<_>>(), ["a", "b", "c"]);
+    /// assert_eq!(iter.return_value(), Some(&Some(2)));
+    /// ```
+    pub async fn yield_flat<Fut2, Resume2, S2>(
+        &self,
+        mut inner: Generator<Item, Fut2, Resume2, S2>,
+    ) -> Option<Fut2::Output>
+    where
+        Fut2: Future + Unpin,
+        Resume2: Default,
+        S2: SharedState<Item, Resume2>,
+    {
+        for item in inner.by_ref() {
+            self.yield_(item).await;
+        }
+        inner.into_return()
+    }
+}
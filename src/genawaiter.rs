@@ -0,0 +1,161 @@
+//! genawaiter's API, for migrating from it.
+//!
+//! [sync], [rc], and [stack] mirror the modules of the same names:
+//! `Gen::new`, `Co::yield_`, `resume`, `resume_with`, and iteration
+//! work as they do there. They're thin wrappers over the crate's
+//! thread-safe, [crate::local], and [crate::scoped] generators.
+//!
+//! ```
+//! use gen::genawaiter::{sync::Gen, GeneratorState};
+//!
+//! let mut generator = Gen::new(|co| async move {
+//!     co.yield_(10).await;
+//!     "done"
+//! });
+//!
+//! assert_eq!(generator.resume(), GeneratorState::Yielded(10));
+//! assert_eq!(generator.resume(), GeneratorState::Complete("done"));
+//! ```
+//!
+//! Differences:
+//! * [stack::Gen::new] is safe, and there's no `let_gen!`; create a
+//!   [stack::Shelf] and pass it to `Gen::new`.
+//! * [stack::Shelf] doesn't take the future's type.
+//! * There's no `async_resume` or `Stream` impl.
+
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+pub use crate::GeneratorState;
+
+/// A generator; [sync::Gen], [rc::Gen], and [stack::Gen] are
+/// this with their storage. `F` may be a `dyn Future`, which
+/// [sync::GenBoxed] and [rc::GenBoxed] use to box the future once.
+pub struct Gen<Y, R, F: Future + ?Sized, S>(crate::Generator<Y, Pin<Box<F>>, R, S>);
+
+impl<Y, R, F: Future + ?Sized, S: crate::shared::SharedState<Y, R>> Gen<Y, R, F, S> {
+    /// Resume the generator, passing `arg` to the waiting `yield_`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning [GeneratorState::Complete].
+    pub fn resume_with(&mut self, arg: R) -> GeneratorState<Y, F::Output> {
+        self.0.resume_with(arg)
+    }
+
+    /// The underlying [crate::Generator]
+    pub fn into_generator(self) -> crate::Generator<Y, Pin<Box<F>>, R, S> {
+        self.0
+    }
+}
+
+impl<Y, F: Future + ?Sized, S: crate::shared::SharedState<Y, ()>> Gen<Y, (), F, S> {
+    /// Resume the generator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning [GeneratorState::Complete].
+    pub fn resume(&mut self) -> GeneratorState<Y, F::Output> {
+        self.0.resume()
+    }
+}
+
+impl<Y, F: Future + ?Sized, S: crate::shared::SharedState<Y, ()>> IntoIterator
+    for Gen<Y, (), F, S>
+{
+    type Item = Y;
+    type IntoIter = crate::Generator<Y, Pin<Box<F>>, (), S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0
+    }
+}
+
+/// Thread-safe generators, like `genawaiter::sync`
+pub mod sync {
+    use crate::SyncShared;
+    use core::future::Future;
+
+    /// Passes items to [Gen]
+    pub type Co<Y, R = ()> = crate::Communication<Y, R>;
+
+    /// A thread-safe generator
+    pub type Gen<Y, R, F> = super::Gen<Y, R, F, SyncShared<Y, R>>;
+
+    /// A [Gen] whose future is boxed, so its type can be named
+    pub type GenBoxed<Y, R = (), C = ()> = Gen<Y, R, dyn Future<Output = C> + Send>;
+
+    impl<Y, R, F: Future> Gen<Y, R, F> {
+        /// Create a generator from a producer, which receives a [Co].
+        pub fn new(producer: impl FnOnce(Co<Y, R>) -> F) -> Self {
+            super::Gen(crate::generate_bidi(producer))
+        }
+    }
+
+    impl<Y, R, C> GenBoxed<Y, R, C> {
+        /// Create a generator whose future is boxed.
+        pub fn new_boxed<F>(producer: impl FnOnce(Co<Y, R>) -> F) -> Self
+        where
+            F: Future<Output = C> + Send + 'static,
+        {
+            super::Gen(crate::generate_bidi(producer).boxed())
+        }
+    }
+}
+
+/// Single-threaded generators, like `genawaiter::rc`
+pub mod rc {
+    use crate::local::LocalShared;
+    use core::future::Future;
+
+    /// Passes items to [Gen]
+    pub type Co<Y, R = ()> = crate::local::Communication<Y, R>;
+
+    /// A single-threaded generator
+    pub type Gen<Y, R, F> = super::Gen<Y, R, F, LocalShared<Y, R>>;
+
+    /// A [Gen] whose future is boxed, so its type can be named
+    pub type GenBoxed<Y, R = (), C = ()> = Gen<Y, R, dyn Future<Output = C>>;
+
+    impl<Y, R, F: Future> Gen<Y, R, F> {
+        /// Create a generator from a producer, which receives a [Co].
+        pub fn new(producer: impl FnOnce(Co<Y, R>) -> F) -> Self {
+            super::Gen(crate::local::generate_bidi(producer))
+        }
+    }
+
+    impl<Y, R, C> GenBoxed<Y, R, C> {
+        /// Create a generator whose future is boxed.
+        pub fn new_boxed<F>(producer: impl FnOnce(Co<Y, R>) -> F) -> Self
+        where
+            F: Future<Output = C> + 'static,
+        {
+            super::Gen(crate::local::generate_bidi(producer).boxed_local())
+        }
+    }
+}
+
+/// Generators whose state lives in caller-owned storage, like
+/// `genawaiter::stack`
+pub mod stack {
+    use crate::scoped::ScopedShared;
+    use core::future::Future;
+
+    /// Storage for a [Gen]
+    pub type Shelf<Y, R = ()> = crate::scoped::Storage<Y, R>;
+
+    /// Passes items to [Gen]
+    pub type Co<'y, Y, R = ()> = crate::scoped::Communication<'y, Y, R>;
+
+    /// A generator whose state lives in a [Shelf]
+    pub type Gen<'s, Y, R, F> = super::Gen<Y, R, F, ScopedShared<'s, Y, R>>;
+
+    impl<'s, Y, R, F: Future> Gen<'s, Y, R, F> {
+        /// Create a generator from a producer, which receives a [Co].
+        ///
+        /// Unlike genawaiter's, this is safe.
+        pub fn new(shelf: &'s mut Shelf<Y, R>, producer: impl FnOnce(Co<'s, Y, R>) -> F) -> Self {
+            super::Gen(crate::scoped::generate_bidi(shelf, producer))
+        }
+    }
+}
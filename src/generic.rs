@@ -0,0 +1,83 @@
+// A trait over every kind of generator.
+
+use crate::{shared::SharedState, Generator, GeneratorState};
+use core::future::Future;
+
+/// A generator, whatever stores its state or runs its async function
+///
+/// [Generator] implements this for every backend: thread-safe,
+/// [crate::local], [crate::scoped], and boxed. Code which accepts
+/// `impl AsyncGenerator<Item>` works with all of them.
+///
+/// ```
+/// use gen::{generate_bidi, local, AsyncGenerator};
+///
+/// // Sends 1, 2, 3, .. until the generator returns
+/// fn count_up<G: AsyncGenerator<i32, Resume = i32>>(mut generator: G) -> Vec<i32> {
+///     let mut items = Vec::new();
+///     let mut sent = 0;
+///     while let Some(item) = generator.send(sent) {
+///         items.push(item);
+///         sent += 1;
+///     }
+///     items
+/// }
+///
+/// let doubler = |co: gen::Communication<i32, i32>| async move {
+///     let mut x = 0;
+///     for _ in 0..3 {
+///         x = co.yield_(x * 2).await;
+///     }
+/// };
+/// assert_eq!(count_up(generate_bidi(doubler)), [0, 2, 4]);
+///
+/// let local = local::generate_bidi(|co| async move {
+///     let mut x = 0;
+///     for _ in 0..3 {
+///         x = co.yield_(x * 10).await;
+///     }
+/// });
+/// assert_eq!(count_up(local), [0, 10, 20]);
+/// ```
+pub trait AsyncGenerator<Item> {
+    /// What `yield_` resolves to
+    type Resume;
+
+    /// What the async function returns
+    type Return;
+
+    /// See [Generator::send]
+    fn send(&mut self, resume: Self::Resume) -> Option<Item>;
+
+    /// See [Generator::resume_with]
+    fn resume_with(&mut self, resume: Self::Resume) -> GeneratorState<Item, Self::Return>;
+
+    /// See [Generator::is_finished]
+    fn is_finished(&self) -> bool;
+
+    /// See [Generator::return_value]
+    fn return_value(&self) -> Option<&Self::Return>;
+}
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>> AsyncGenerator<Item>
+    for Generator<Item, Fut, Resume, S>
+{
+    type Resume = Resume;
+    type Return = Fut::Output;
+
+    fn send(&mut self, resume: Resume) -> Option<Item> {
+        Generator::send(self, resume)
+    }
+
+    fn resume_with(&mut self, resume: Resume) -> GeneratorState<Item, Fut::Output> {
+        Generator::resume_with(self, resume)
+    }
+
+    fn is_finished(&self) -> bool {
+        Generator::is_finished(self)
+    }
+
+    fn return_value(&self) -> Option<&Fut::Output> {
+        Generator::return_value(self)
+    }
+}
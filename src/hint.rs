@@ -0,0 +1,149 @@
+// Size hints from the async function.
+
+use crate::{shared::SharedState, Communication, Generator, SyncShared};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Version of [crate::generate] whose [Iterator::size_hint] starts at
+/// `hint`.
+///
+/// Each item yielded counts against the bounds. The async function
+/// may revise them with [Communication::set_remaining].
+///
+/// ```
+/// use gen::generate_with_hint;
+///
+/// let mut iter = generate_with_hint((3, Some(3)), |co| async move {
+///     co.yield_all(["a", "b", "c"]).await;
+/// });
+///
+/// assert_eq!(iter.size_hint(), (3, Some(3)));
+/// iter.next();
+/// assert_eq!(iter.size_hint(), (2, Some(2)));
+/// ```
+pub fn generate_with_hint<Item, F, Fut>(
+    hint: (usize, Option<usize>),
+    f: F,
+) -> Generator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let generator = crate::generate(f);
+    generator.shared.with(|slot| slot.remaining = hint);
+    generator
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Promise to yield exactly `n` more items. [Iterator::size_hint]
+    /// reports this from then on, so adapters like `collect` can
+    /// allocate once they've seen the first item.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     let words = ["x", "y", "z"];
+    ///     co.set_remaining(words.len());
+    ///     co.yield_all(words).await;
+    /// });
+    ///
+    /// assert_eq!(iter.size_hint(), (0, None));
+    /// assert_eq!(iter.next(), Some("x"));
+    /// assert_eq!(iter.size_hint(), (2, Some(2)));
+    /// ```
+    pub fn set_remaining(&self, n: usize) {
+        self.0.with(|slot| slot.remaining = (n, Some(n)));
+    }
+}
+
+/// Version of [crate::generate] for an async function which yields
+/// exactly `n` items. The result is an [ExactSizeIterator].
+///
+/// In debug builds, iteration panics if the async function yields
+/// more or fewer items.
+///
+/// ```
+/// use gen::generate_exact;
+///
+/// let squares = generate_exact(4, |co| async move {
+///     for i in 0..4 {
+///         co.yield_(i * i).await;
+///     }
+/// });
+///
+/// assert_eq!(squares.len(), 4);
+/// assert_eq!(squares.collect::<Vec<_>>(), [0, 1, 4, 9]);
+/// ```
+pub fn generate_exact<Item, F, Fut>(n: usize, f: F) -> ExactGenerator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    ExactGenerator {
+        generator: crate::generate(f),
+        remaining: n,
+    }
+}
+
+/// An iterator over a known number of items yielded by an async
+/// function
+///
+/// [generate_exact] returns this.
+pub struct ExactGenerator<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    generator: Generator<Item, Fut, Resume, S>,
+    remaining: usize,
+}
+
+impl<Item, Fut: Future, Resume, S> ExactGenerator<Item, Fut, Resume, S> {
+    /// The underlying [Generator]
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.generator
+    }
+
+    /// Consume this, returning the underlying [Generator].
+    pub fn into_generator(self) -> Generator<Item, Fut, Resume, S> {
+        self.generator
+    }
+}
+
+impl<Item, Fut, Resume, S> Iterator for ExactGenerator<Item, Fut, Resume, S>
+where
+    Fut: Future + Unpin,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.generator.next();
+        match item {
+            Some(_) => {
+                debug_assert!(self.remaining > 0, "generate_exact yielded too many items");
+                self.remaining = self.remaining.saturating_sub(1);
+            }
+            None => debug_assert!(self.remaining == 0, "generate_exact yielded too few items"),
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<Item, Fut, Resume, S> ExactSizeIterator for ExactGenerator<Item, Fut, Resume, S>
+where
+    Fut: Future + Unpin,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+}
+
+impl<Item, Fut, Resume, S> core::iter::FusedIterator for ExactGenerator<Item, Fut, Resume, S>
+where
+    Fut: Future + Unpin,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+}
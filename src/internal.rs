@@ -0,0 +1,50 @@
+// Internal iteration: the generator pushes items to a callback.
+
+use crate::{local, GeneratorState};
+use core::{future::Future, ops::ControlFlow};
+
+/// Run an async function, passing each item it yields to `consume`.
+///
+/// `consume` returns [ControlFlow::Break] to stop early; `drive` then
+/// drops the async function and returns the break value. Otherwise it
+/// returns what the async function returned.
+///
+/// This is a loop over [local::generate]: items still pass through
+/// the generator's shared state, and can't borrow the async
+/// function's locals. It saves writing the loop, and uses
+/// single-threaded storage even for a `Send` async function.
+///
+/// ```
+/// use gen::drive;
+/// use std::ops::ControlFlow;
+///
+/// let found = drive(
+///     |co| async move {
+///         for i in 1.. {
+///             co.yield_(i * i).await;
+///         }
+///     },
+///     |square| match square > 50 {
+///         true => ControlFlow::Break(square),
+///         false => ControlFlow::Continue(()),
+///     },
+/// );
+///
+/// assert_eq!(found, ControlFlow::Break(64));
+/// ```
+pub fn drive<Item, B, F, Fut>(
+    f: F,
+    mut consume: impl FnMut(Item) -> ControlFlow<B>,
+) -> ControlFlow<B, Fut::Output>
+where
+    F: FnOnce(local::Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let mut generator = local::generate(f);
+    loop {
+        match generator.resume() {
+            GeneratorState::Yielded(item) => consume(item)?,
+            GeneratorState::Complete(value) => return ControlFlow::Continue(value),
+        }
+    }
+}
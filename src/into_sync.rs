@@ -0,0 +1,45 @@
+// A method-call entry point to generate.
+
+use crate::{generate, Communication, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Things which become synchronous iterators over `Item`s
+///
+/// Functions which take a [Communication] get this, as a method-call
+/// spelling of [generate]. Closures need their argument's type
+/// spelled out, since nothing else pins down `Item`.
+///
+/// ```
+/// use gen::{Communication, IntoSyncIterator};
+///
+/// async fn countdown(co: Communication<u32>) {
+///     for i in (1..=3).rev() {
+///         co.yield_(i).await;
+///     }
+/// }
+///
+/// assert_eq!(countdown.into_sync_iter().collect::<Vec<_>>(), [3, 2, 1]);
+///
+/// let squares = |co: Communication<u32>| async move { co.yield_all([1, 4, 9]).await };
+/// assert_eq!(squares.into_sync_iter().sum::<u32>(), 14);
+/// ```
+pub trait IntoSyncIterator<Item> {
+    /// The iterator this becomes
+    type IntoIter: Iterator<Item = Item>;
+
+    /// Start iterating.
+    fn into_sync_iter(self) -> Self::IntoIter;
+}
+
+impl<Item, F, Fut> IntoSyncIterator<Item> for F
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    type IntoIter = Generator<Item, Pin<Box<Fut>>>;
+
+    fn into_sync_iter(self) -> Self::IntoIter {
+        generate(self)
+    }
+}
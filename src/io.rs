@@ -0,0 +1,125 @@
+// Byte generators as std::io readers.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use std::{future::Future, io};
+
+impl<Item: AsRef<[u8]>, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Read the bytes of each yielded chunk in turn.
+    ///
+    /// Chunks are pulled from the async function only as reads need
+    /// them. End of file is when the async function returns. The
+    /// reader also implements [BufRead](io::BufRead), whose buffer is
+    /// the current chunk.
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::io::Read;
+    ///
+    /// let mut reader = generate(|co| async move {
+    ///     for word in ["lazy", " ", "bytes"] {
+    ///         co.yield_(word).await;
+    ///     }
+    /// })
+    /// .into_read();
+    ///
+    /// let mut text = String::new();
+    /// reader.read_to_string(&mut text).unwrap();
+    /// assert_eq!(text, "lazy bytes");
+    /// ```
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::io::BufRead;
+    ///
+    /// let reader = generate(|co| async move {
+    ///     co.yield_("first\nsec").await;
+    ///     co.yield_("ond\nthird").await;
+    /// })
+    /// .into_read();
+    ///
+    /// let lines = reader.lines().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(lines, ["first", "second", "third"]);
+    /// ```
+    pub fn into_read(self) -> GeneratorReader<Item, Fut, Resume, S> {
+        GeneratorReader {
+            generator: self,
+            chunk: None,
+            position: 0,
+        }
+    }
+}
+
+/// Reads the bytes a [Generator] yields
+///
+/// [Generator::into_read] returns this.
+pub struct GeneratorReader<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    generator: Generator<Item, Fut, Resume, S>,
+    // The chunk being read, and how much of it has been
+    chunk: Option<Item>,
+    position: usize,
+}
+
+impl<Item, Fut: Future, Resume, S> GeneratorReader<Item, Fut, Resume, S> {
+    /// The underlying [Generator]
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.generator
+    }
+
+    /// Consume this, returning the underlying [Generator]. Unread
+    /// bytes of the current chunk are lost.
+    pub fn into_generator(self) -> Generator<Item, Fut, Resume, S> {
+        self.generator
+    }
+}
+
+impl<Item: AsRef<[u8]>, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    GeneratorReader<Item, Fut, Resume, S>
+{
+    // The unread part of the current chunk, pulling the next nonempty
+    // chunk if needed. Empty at end of file.
+    fn unread(&mut self) -> &[u8] {
+        while self
+            .chunk
+            .as_ref()
+            .is_none_or(|chunk| self.position == chunk.as_ref().len())
+        {
+            self.position = 0;
+            self.chunk = self.generator.next();
+            if self.chunk.is_none() {
+                return &[];
+            }
+        }
+        match &self.chunk {
+            Some(chunk) => &chunk.as_ref()[self.position..],
+            None => &[],
+        }
+    }
+}
+
+impl<Item: AsRef<[u8]>, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> io::Read
+    for GeneratorReader<Item, Fut, Resume, S>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let unread = self.unread();
+        let len = unread.len().min(buf.len());
+        buf[..len].copy_from_slice(&unread[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+// The current chunk is the buffer, so nothing is copied.
+impl<Item: AsRef<[u8]>, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    io::BufRead for GeneratorReader<Item, Fut, Resume, S>
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.unread())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let len = self.chunk.as_ref().map_or(0, |chunk| chunk.as_ref().len());
+        self.position = (self.position + amount).min(len);
+    }
+}
@@ -0,0 +1,110 @@
+// Generators which lend items instead of giving them away.
+
+use crate::{shared::SharedState, Communication, Generator, SyncShared};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// An iterator whose items borrow from the iterator
+///
+/// [Iterator] can't express this; its items outlive the call to
+/// `next` that produced them.
+pub trait LendingIterator {
+    /// The items, which borrow the iterator for `'a`
+    type Item<'a>
+    where
+        Self: 'a;
+
+    /// Get the next item. It must be dropped before calling `next`
+    /// again.
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+}
+
+/// Turn an async function into a [LendingIterator].
+///
+/// The async function lends each item with
+/// [Communication::yield_ref], which resolves to the same item once
+/// the consumer is done with it. Reusing a buffer this way avoids
+/// allocating or cloning per item.
+///
+/// ```
+/// use gen::{generate_lending, LendingIterator};
+///
+/// let text = "one\ntwo\nthree";
+/// let mut lines = generate_lending(|co| async move {
+///     let mut line = String::new();
+///     for c in text.chars() {
+///         if c == '\n' {
+///             line = co.yield_ref(line).await;
+///             line.clear();
+///         } else {
+///             line.push(c);
+///         }
+///     }
+///     co.yield_ref(line).await;
+/// });
+///
+/// let mut lengths = Vec::new();
+/// while let Some(line) = lines.next() {
+///     lengths.push(line.len());
+/// }
+/// assert_eq!(lengths, [3, 3, 5]);
+/// ```
+pub fn generate_lending<Item, F, Fut>(f: F) -> LendingGenerator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item, Option<Item>>) -> Fut,
+    Fut: Future,
+{
+    LendingGenerator {
+        generator: crate::generate_bidi(f),
+        lent: None,
+    }
+}
+
+/// A generator which lends each item to the consumer, then hands it
+/// back to the async function
+///
+/// [generate_lending] returns this.
+pub struct LendingGenerator<Item, Fut: Future, S = SyncShared<Item, Option<Item>>> {
+    generator: Generator<Item, Fut, Option<Item>, S>,
+    lent: Option<Item>,
+}
+
+impl<Item, Fut: Future, S> LendingGenerator<Item, Fut, S> {
+    /// The underlying [Generator]
+    pub fn generator(&self) -> &Generator<Item, Fut, Option<Item>, S> {
+        &self.generator
+    }
+}
+
+impl<Item, Fut, S> LendingIterator for LendingGenerator<Item, Fut, S>
+where
+    Fut: Future + Unpin,
+    S: SharedState<Item, Option<Item>>,
+{
+    type Item<'a>
+        = &'a Item
+    where
+        Self: 'a;
+
+    fn next(&mut self) -> Option<&Item> {
+        let lent = self.lent.take();
+        self.lent = self.generator.send(lent);
+        self.lent.as_ref()
+    }
+}
+
+impl<Item, S: SharedState<Item, Option<Item>>> Communication<Item, Option<Item>, S> {
+    /// Lend `item` to [LendingGenerator], resolving to it once the
+    /// consumer asks for the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if resumed without the item, i.e. by
+    /// [Generator::send]ing `None` instead of through
+    /// [LendingGenerator].
+    pub async fn yield_ref(&self, item: Item) -> Item {
+        self.yield_(item)
+            .await
+            .expect("yield_ref resumed without its item")
+    }
+}
@@ -35,78 +35,1102 @@
 //!
 //! println!("done");
 //! ```
+//!
+//! ## Features
+//!
+//! * `std` (default): without it, the crate is `#![no_std]` and only
+//!   needs `alloc`. There's no `Mutex` then, so [Generator] can't move
+//!   between threads, unless `spin` is on.
+//! * `spin`: without `std`, guard shared state with a spinlock, so
+//!   generators can move between threads or cores.
+//! * `nightly`: implement [std::async_iter::AsyncIterator] and
+//!   [std::ops::Coroutine] for [Generator], and add `from_coroutine`,
+//!   `generate_in`, and `Communication::forward`.
+//!   Requires a nightly compiler.
+//! * `macros`: add the `generator` attribute and `gen!` macro.
+//! * `ffi`: add the `ffi` module, for driving generators from C.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(
+    feature = "nightly",
+    feature(allocator_api, async_iterator, coroutine_trait)
+)]
+
+extern crate alloc;
 
-use std::{
+use alloc::{boxed::Box, sync::Arc, task::Wake};
+#[cfg(feature = "std")]
+use core::any::Any;
+use core::{
     future::Future,
+    marker::PhantomData,
     pin::Pin,
-    sync::{Arc, Mutex},
-    task::{Context, Poll, Wake},
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
 };
 
+mod boxed;
+mod budget;
+mod buffered;
+mod builder;
+mod cancel;
+mod chunk;
+mod convert;
+mod debug;
+mod driver;
+mod error;
+mod fallible;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod flatten;
+pub mod genawaiter;
+mod generic;
+mod hint;
+mod internal;
+mod into_sync;
+#[cfg(feature = "std")]
+mod io;
+mod lending;
+pub mod local;
+mod map;
+mod memoize;
+mod merge;
+mod metadata;
+mod pair;
+mod parts;
+mod peekable;
+mod pending;
+#[cfg(feature = "std")]
+mod prefetch;
+mod progress;
+mod restart;
+pub mod scheduler;
+pub mod scoped;
+mod shared;
+mod sources;
+mod tee;
+mod ticker;
+#[cfg(feature = "std")]
+mod time;
+
+#[cfg(feature = "std")]
+pub use boxed::generate_send;
+pub use boxed::{BoxGenerator, LocalBoxGenerator};
+pub use budget::{Budgeted, YieldNow};
+pub use buffered::generate_buffered;
+pub use builder::GenBuilder;
+pub use cancel::{CancellationToken, Cancelled};
+pub use driver::{generate_with_driver, Driver};
+pub use error::Error;
+pub use fallible::{try_generate, TryGenerator};
+pub use flatten::Flatten;
+#[cfg(feature = "macros")]
+pub use gen_macros::{gen, generator};
+pub use generic::AsyncGenerator;
+pub use hint::{generate_exact, generate_with_hint, ExactGenerator};
+pub use internal::drive;
+pub use into_sync::IntoSyncIterator;
+#[cfg(feature = "std")]
+pub use io::GeneratorReader;
+pub use lending::{generate_lending, LendingGenerator, LendingIterator};
+pub use map::{MapItems, MapReturn};
+pub use memoize::{Memoized, Replay};
+pub use merge::{merge, merge_sorted_by_key, Merge, MergeSorted};
+pub use pair::Attach;
+pub use parts::Shared;
+pub use peekable::PeekableGenerator;
+#[cfg(feature = "std")]
+pub use prefetch::{generate_from_write, generate_prefetch, ChunkWriter, Prefetch};
+pub use progress::Progress;
+pub use restart::{generate_restartable, Restartable};
+use shared::{Lock, SharedState, Slot};
+pub use sources::{from_fn_async, once_future, successors_async, unfold};
+pub use tee::Tee;
+pub use ticker::{tick, Tick, TickFuture, Ticker};
+#[cfg(feature = "std")]
+pub use time::{Sleep, Throttle, TimedOut};
+
 /// Turn an async function into a fully-synchronous [Iterator].
 ///
 /// See [crate documentation](crate) for usage.
-pub fn generate<Item, F, Fut>(f: F) -> Generator<Item, Fut>
+///
+/// The async function may return a value. [Generator::return_value]
+/// and [Generator::into_return] retrieve it once iteration finishes.
+pub fn generate<Item, F, Fut>(f: F) -> Generator<Item, Pin<Box<Fut>>>
 where
     F: FnOnce(Communication<Item>) -> Fut,
-    Fut: Future<Output = ()>,
+    Fut: Future,
 {
-    let shared: SharedState<Item> = Default::default();
-    let future = Box::pin(f(Communication(shared.clone())));
-    Generator {
-        shared,
-        future,
-        done: false,
-    }
+    generate_bidi(f)
+}
+
+/// Turn an async function into a bidirectional generator.
+///
+/// `co.yield_(item).await` resolves to the value the consumer passes
+/// to [Generator::send]. [Iterator::next] sends `Resume::default()`.
+/// The value passed to the first `send` starts the async function;
+/// no `yield_` is waiting to receive it, so it is dropped.
+///
+/// ```
+/// use gen::generate_bidi;
+///
+/// let mut running_total = generate_bidi(|co| async move {
+///     let mut total = 0;
+///     loop {
+///         total += co.yield_(total).await;
+///     }
+/// });
+///
+/// assert_eq!(running_total.send(0), Some(0));
+/// assert_eq!(running_total.send(5), Some(5));
+/// assert_eq!(running_total.send(2), Some(7));
+/// ```
+pub fn generate_bidi<Item, Resume, F, Fut>(f: F) -> Generator<Item, Pin<Box<Fut>>, Resume>
+where
+    F: FnOnce(Communication<Item, Resume>) -> Fut,
+    Fut: Future,
+{
+    Generator::new_boxed(Default::default(), f)
+}
+
+/// Version of [generate] which contains panics from the async function.
+///
+/// If the async function panics, [Iterator::next] returns `None`
+/// instead of unwinding, the generator finishes, and
+/// [Generator::panic_payload] holds the panic's payload.
+///
+/// ```
+/// use gen::generate_catching;
+///
+/// let mut iter = generate_catching(|co| async move {
+///     co.yield_(1).await;
+///     panic!("bad input");
+/// });
+///
+/// assert_eq!(iter.next(), Some(1));
+/// assert_eq!(iter.next(), None);
+/// let payload = iter.panic_payload().unwrap();
+/// assert_eq!(payload.downcast_ref::<&str>(), Some(&"bad input"));
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_catching<Item, F, Fut>(f: F) -> Generator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let mut generator = generate(f);
+    generator.set_catch_panics(true);
+    generator
+}
+
+/// Version of [generate] which stores an [Unpin] future inline
+/// instead of boxing it.
+///
+/// Async blocks aren't `Unpin`; this suits hand-written futures and
+/// combinators of them.
+///
+/// ```
+/// use gen::{generate_unpin, Communication};
+/// use std::{
+///     future::Future,
+///     pin::Pin,
+///     task::{Context, Poll},
+/// };
+///
+/// struct Countdown {
+///     co: Communication<u32>,
+///     n: u32,
+/// }
+///
+/// impl Future for Countdown {
+///     type Output = ();
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+///         if self.n == 0 {
+///             return Poll::Ready(());
+///         }
+///         self.n -= 1;
+///         // Queues the item. The generator takes it once this
+///         // returns Pending.
+///         let mut item = self.co.yield_(self.n);
+///         let _ = Pin::new(&mut item).poll(cx);
+///         Poll::Pending
+///     }
+/// }
+///
+/// let iter = generate_unpin(|co| Countdown { co, n: 3 });
+/// assert_eq!(iter.collect::<Vec<_>>(), [2, 1, 0]);
+/// ```
+pub fn generate_unpin<Item, F, Fut>(f: F) -> Generator<Item, Fut>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future + Unpin,
+{
+    let shared = SyncShared::default();
+    let future = f(Communication::new(shared.clone()));
+    Generator::new(shared, Some(future))
+}
+
+/// Version of [generate] which allocates the future with
+/// `allocator`, e.g. an arena for many short-lived generators.
+///
+/// The shared state still uses the global allocator.
+#[cfg(feature = "nightly")]
+pub fn generate_in<Item, A, F, Fut>(allocator: A, f: F) -> Generator<Item, Pin<Box<Fut, A>>>
+where
+    A: core::alloc::Allocator + 'static,
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    let shared = SyncShared::default();
+    let future = Box::pin_in(f(Communication::new(shared.clone())), allocator);
+    Generator::new(shared, Some(future))
+}
+
+/// Turn a [core::ops::Coroutine] into a [Generator].
+///
+/// The coroutine's first resume receives `Resume::default()`, since
+/// the value passed to a generator's first `send` is dropped.
+#[cfg(feature = "nightly")]
+pub fn from_coroutine<Resume, C>(
+    coroutine: C,
+) -> Generator<C::Yield, Pin<Box<impl Future<Output = C::Return>>>, Resume>
+where
+    Resume: Default,
+    C: core::ops::Coroutine<Resume>,
+{
+    generate_bidi(|co| async move {
+        let mut coroutine = core::pin::pin!(coroutine);
+        let mut resume = Resume::default();
+        loop {
+            match coroutine.as_mut().resume(resume) {
+                core::ops::CoroutineState::Yielded(item) => resume = co.yield_(item).await,
+                core::ops::CoroutineState::Complete(value) => return value,
+            }
+        }
+    })
 }
 
 // Shared state between Communication and Generator.
 //
-// Rc<RefCell<Option<Item>>> would work, but would prevent
+// Rc<RefCell<Slot<..>>> would work, but would prevent
 // Generator from being able to move between threads.
-type SharedState<Item> = Arc<Mutex<Option<Item>>>;
+// gen::local uses that instead.
+type SyncShared<Item, Resume> = Arc<Lock<Slot<Item, Resume>>>;
 
 /// An iterator which synchronously produces items yielded by an async function.
 ///
 /// [generate] returns this. See [crate documentation](crate) for usage.
-pub struct Generator<Item, Fut: Future<Output = ()>> {
-    shared: SharedState<Item>,
-    future: Pin<Box<Fut>>,
+///
+/// `Fut` is the async function's future, already pinned; that's
+/// `Pin<Box<..>>` for [generate].
+pub struct Generator<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    shared: S,
+    future: Option<Fut>,
+    started: bool,
     done: bool,
+    // Times the future was polled, and polls which didn't yield
+    polls: u64,
+    idle_polls: u64,
+    poisoned: bool,
+    wake_flag: Option<(Arc<WakeFlag>, Waker)>,
+    driver: Option<Box<dyn Driver + Send + Sync>>,
+    return_value: Option<Fut::Output>,
+    on_drop: Option<OnDrop<Self, Resume>>,
+    // Releases undelivered items and runs the async function's
+    // on_cancel callbacks. Drop can't call SharedState's methods itself.
+    cleanup: Option<fn(&mut Self)>,
+    #[cfg(feature = "std")]
+    catch_panics: bool,
+    // Locked only so the payload, which needn't be Sync, doesn't make
+    // Generator !Sync; it's reached through &mut self.
+    #[cfg(feature = "std")]
+    panic: Lock<Option<Box<dyn Any + Send>>>,
+    item: PhantomData<fn() -> (Item, Resume)>,
 }
 
-impl<Item, Fut: Future<Output = ()>> Iterator for Generator<Item, Fut> {
-    type Item = Item;
+// What to run when Generator drops, the policy to pass it, and how to
+// make resume values
+type OnDrop<G, Resume> = (
+    fn(&mut G, DropPolicy, fn() -> Resume),
+    DropPolicy,
+    fn() -> Resume,
+);
 
-    fn next(&mut self) -> Option<Self::Item> {
+impl<Item, Inner: Future, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Pin<Box<Inner>>, Resume, S>
+{
+    fn new_boxed<F>(shared: S, f: F) -> Self
+    where
+        F: FnOnce(Communication<Item, Resume, S>) -> Inner,
+    {
+        let future = Box::pin(f(Communication::new(shared.clone())));
+        Generator::new(shared, Some(future))
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    // A generator with no future is finished
+    fn new(shared: S, future: Option<Fut>) -> Self {
+        shared.with(|slot| slot.driven = true);
+        Generator {
+            shared,
+            done: future.is_none(),
+            future,
+            started: false,
+            polls: 0,
+            idle_polls: 0,
+            poisoned: false,
+            wake_flag: None,
+            driver: None,
+            return_value: None,
+            on_drop: None,
+            cleanup: Some(Self::cleanup),
+            #[cfg(feature = "std")]
+            catch_panics: false,
+            #[cfg(feature = "std")]
+            panic: Lock::new(None),
+            item: PhantomData,
+        }
+    }
+
+    /// Whether the async function has returned, was closed, or
+    /// panicked. A finished generator won't poll the async function
+    /// again.
+    pub fn is_finished(&self) -> bool {
+        self.done
+    }
+
+    /// Whether the async function panicked, whether or not the panic
+    /// was caught. A poisoned generator is finished.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Where the generator is in its life
+    pub fn state(&self) -> Status {
+        if self.poisoned {
+            Status::Poisoned
+        } else if self.done {
+            Status::Completed
+        } else if self.started {
+            Status::Suspended
+        } else {
+            Status::NotStarted
+        }
+    }
+
+    /// How many items the generator has produced
+    pub fn items_so_far(&self) -> u64 {
+        self.shared.with(|slot| slot.delivered)
+    }
+
+    /// How many items the async function has yielded. This counts
+    /// items still queued by [generate_buffered].
+    pub fn items_yielded(&self) -> u64 {
+        self.shared
+            .with(|slot| slot.delivered + slot.items.len() as u64)
+    }
+
+    /// How many times the generator has polled the async function
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// How many polls of the async function left it waiting on
+    /// something other than `yield_`
+    pub fn resumes_without_yield(&self) -> u64 {
+        self.idle_polls
+    }
+
+    /// The value returned by the async function, if it has finished.
+    ///
+    /// This is `None` until [Iterator::next] returns `None`.
+    pub fn return_value(&self) -> Option<&Fut::Output> {
+        self.return_value.as_ref()
+    }
+
+    /// Consume the generator, returning the value returned by the
+    /// async function, if it has finished.
+    pub fn into_return(mut self) -> Option<Fut::Output> {
+        self.return_value.take()
+    }
+
+    /// Pass `resume` to the waiting `yield_` and run the async function
+    /// until it yields an item or returns.
+    ///
+    /// Returns `None` once the async function has returned, like
+    /// [Iterator::next].
+    pub fn send(&mut self, resume: Resume) -> Option<Item> {
         if self.done {
             return None;
         }
+        match self.resume_inner(resume)? {
+            GeneratorState::Yielded(item) => Some(item),
+            GeneratorState::Complete(value) => {
+                self.return_value = Some(value);
+                None
+            }
+        }
+    }
+
+    /// Pass `resume` to the waiting `yield_` and run the async function
+    /// until it yields an item or returns.
+    ///
+    /// Unlike [Generator::send], this distinguishes a yielded item from
+    /// completion. The return value moves out of the generator, so
+    /// [Generator::return_value] is `None` afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning [GeneratorState::Complete],
+    /// or if the async function panicked, even if the panic was caught;
+    /// see [generate_catching].
+    pub fn resume_with(&mut self, resume: Resume) -> GeneratorState<Item, Fut::Output> {
+        match self.try_resume_with(resume) {
+            Ok(state) => state,
+            Err(error) => error::misuse(error).expect("Generator's async function panicked"),
+        }
+    }
+
+    /// Like [Generator::resume_with], but misuse it would panic over
+    /// comes back as an error.
+    ///
+    /// ```
+    /// use gen::{generate_bidi, Error, GeneratorState};
+    ///
+    /// let mut generator = generate_bidi(|co| async move {
+    ///     co.yield_(1).await;
+    /// });
+    ///
+    /// assert_eq!(generator.try_resume_with(()), Ok(GeneratorState::Yielded(1)));
+    /// assert_eq!(generator.try_resume_with(()), Ok(GeneratorState::Complete(())));
+    /// assert_eq!(generator.try_resume_with(()), Err(Error::Closed));
+    /// ```
+    pub fn try_resume_with(
+        &mut self,
+        resume: Resume,
+    ) -> Result<GeneratorState<Item, Fut::Output>, Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+        if self.done {
+            return self
+                .return_value
+                .take()
+                .map(GeneratorState::Complete)
+                .ok_or(Error::Closed);
+        }
+        self.try_resume_inner(resume)
+    }
+
+    // Execute future until it yields a new value or finishes.
+    // Returns `None` if it panicked and the panic was caught.
+    fn resume_inner(&mut self, resume: Resume) -> Option<GeneratorState<Item, Fut::Output>> {
+        match self.try_resume_inner(resume) {
+            Ok(state) => Some(state),
+            Err(error) => error::misuse(error),
+        }
+    }
 
-        struct Waker;
-        impl Wake for Waker {
-            fn wake(self: Arc<Self>) {}
+    // Like resume_inner, but reports misuse instead of panicking
+    fn try_resume_inner(
+        &mut self,
+        resume: Resume,
+    ) -> Result<GeneratorState<Item, Fut::Output>, Error> {
+        let mut resume = Some(resume);
+        if let Some(mut driver) = self.driver.take() {
+            let mut state = None;
+            driver.block_on(core::pin::pin!(core::future::poll_fn(|cx| {
+                self.poll_resume(resume.take(), cx)
+                    .map(|ready| state = Some(ready))
+            })));
+            self.driver = Some(driver);
+            return state.expect("Driver returned before the future finished");
+        }
+        loop {
+            let waker = self.sync_waker();
+            if let Poll::Ready(state) =
+                self.poll_resume(resume.take(), &mut Context::from_waker(&waker))
+            {
+                return state;
+            }
+            if !self.woken() {
+                return Err(Error::Stalled);
+            }
+        }
+    }
+
+    // The waker for polling without a driver. `yield_` never wakes,
+    // so until the async function awaits something else, this is a
+    // static no-op and resuming doesn't allocate.
+    fn sync_waker(&mut self) -> Waker {
+        match &self.wake_flag {
+            Some((flag, waker)) => {
+                flag.0.store(false, Ordering::Relaxed);
+                waker.clone()
+            }
+            None => Waker::noop().clone(),
+        }
+    }
+
+    // After a poll with sync_waker returned Pending, whether polling
+    // again could make progress. The async function is awaiting
+    // something other than `yield_`; poll it again only if that
+    // something asked for it, or we'd spin forever.
+    fn woken(&mut self) -> bool {
+        match &self.wake_flag {
+            None => {
+                let flag = Arc::new(WakeFlag(AtomicBool::new(false)));
+                self.wake_flag = Some((flag.clone(), flag.into()));
+                true
+            }
+            Some((flag, _)) => flag.0.load(Ordering::Relaxed),
         }
-        let waker = Arc::new(Waker).into();
+    }
 
-        // Execute future until it yields a new value or finishes.
-        while self
-            .future
-            .as_mut()
-            .poll(&mut Context::from_waker(&waker))
-            .is_pending()
-        {
-            let out = self.shared.lock().unwrap().take();
-            if out.is_some() {
-                return out;
+    // Poll the async function once, passing `resume` to the waiting
+    // `yield_`. `Pending` means the async function is waiting on
+    // something other than `yield_`. Discards the resume value if
+    // nothing was waiting for it. A caught panic is Error::Panicked.
+    fn poll_resume(
+        &mut self,
+        resume: Option<Resume>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<GeneratorState<Item, Fut::Output>, Error>> {
+        self.started = true;
+        let queued = self.shared.with(|slot| {
+            // It's for the yield_ whose item Generator took last
+            if let (Some(resume), Some(ticket)) = (resume, slot.delivered.checked_sub(1)) {
+                slot.resume = Some((ticket, resume));
+            }
+            // Drain what a buffered generator ran ahead to produce, or
+            // the rest of a chunk, unless closing
+            if !slot.closed && (slot.buffer.is_some() || slot.delivered < slot.chunk_end) {
+                slot.pop()
+            } else {
+                None
+            }
+        });
+        if let Some(item) = queued {
+            return Poll::Ready(Ok(GeneratorState::Yielded(item)));
+        }
+        if self.future.is_none() {
+            // It returned while items were still buffered
+            self.done = true;
+            return Poll::Ready(
+                self.return_value
+                    .take()
+                    .map(GeneratorState::Complete)
+                    .ok_or(Error::Panicked),
+            );
+        }
+        let Some(poll) = self.poll_future(cx) else {
+            self.future = None;
+            let items = self.shared.with(|slot| {
+                slot.resume = None;
+                slot.take_items()
+            });
+            self.release(items);
+            self.done = true;
+            return Poll::Ready(Err(Error::Panicked));
+        };
+        match poll {
+            Poll::Pending => {
+                let (error, item) = self.shared.with(|slot| match slot.error.take() {
+                    Some(error) => (Some(error), None),
+                    None => (None, slot.pop()),
+                });
+                if let Some(error) = error {
+                    // The future can't make progress; finish poisoned,
+                    // as if the yield_ had panicked
+                    self.future = None;
+                    self.done = true;
+                    self.poisoned = true;
+                    return Poll::Ready(Err(error));
+                }
+                match item {
+                    Some(item) => {
+                        self.shared.with(|slot| slot.resume = None);
+                        Poll::Ready(Ok(GeneratorState::Yielded(item)))
+                    }
+                    None => {
+                        self.idle_polls += 1;
+                        Poll::Pending
+                    }
+                }
+            }
+            Poll::Ready(value) => {
+                // Release whatever the async function captured now
+                // instead of when the generator drops. Items from
+                // yield_s it abandoned go with it, unless buffered.
+                self.future = None;
+                let (item, abandoned) = self.shared.with(|slot| {
+                    slot.resume = None;
+                    slot.on_cancel.clear();
+                    match slot.buffer {
+                        Some(_) if !slot.closed => (slot.pop(), Default::default()),
+                        _ => (None, slot.take_items()),
+                    }
+                });
+                self.release(abandoned);
+                if let Some(item) = item {
+                    self.return_value = Some(value);
+                    return Poll::Ready(Ok(GeneratorState::Yielded(item)));
+                }
+                self.done = true;
+                Poll::Ready(Ok(GeneratorState::Complete(value)))
             }
         }
+    }
 
+    // Poll the future once. Returns `None` if it panicked and the
+    // panic was caught. A panic that isn't caught unwinds with the
+    // generator finished and poisoned, so the future is never polled
+    // again.
+    fn poll_future(&mut self, cx: &mut Context<'_>) -> Option<Poll<Fut::Output>> {
+        self.polls += 1;
         self.done = true;
-        None
+        self.poisoned = true;
+        let future = self.future.as_mut().unwrap();
+        #[cfg(feature = "std")]
+        let poll = if self.catch_panics {
+            let poll = || Pin::new(future).poll(cx);
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(poll)) {
+                Ok(poll) => poll,
+                Err(payload) => {
+                    *shared::get_mut(&mut self.panic) = Some(payload);
+                    return None;
+                }
+            }
+        } else {
+            Pin::new(future).poll(cx)
+        };
+        #[cfg(not(feature = "std"))]
+        let poll = Pin::new(future).poll(cx);
+        self.done = false;
+        self.poisoned = false;
+        Some(poll)
+    }
+
+    // Generator::close, resolving the waiting yield_s to `resume()`
+    fn close_with(&mut self, resume: fn() -> Resume) {
+        if !self.done {
+            // Queued items won't be delivered; releasing them lets
+            // their yield_s resolve
+            let queued = self.shared.with(|slot| {
+                slot.closed = true;
+                slot.deliver_all(resume)
+            });
+            self.release(queued);
+            if self.started {
+                match self.resume_inner(resume()) {
+                    Some(GeneratorState::Yielded(item)) => self.release([item]),
+                    Some(GeneratorState::Complete(value)) => self.return_value = Some(value),
+                    // Panicked; the future is gone
+                    None => {}
+                }
+            }
+            self.done = true;
+        }
+        self.future = None;
+        self.run_cancel_hooks();
+    }
+
+    // Apply a DropPolicy other than Drop
+    fn drop_with(&mut self, policy: DropPolicy, resume: fn() -> Resume) {
+        match policy {
+            DropPolicy::Drop => {}
+            DropPolicy::Close => self.close_with(resume),
+            DropPolicy::Complete => self.complete_with(resume),
+        }
+    }
+
+    // Run the async function to completion, releasing what it yields
+    fn complete_with(&mut self, resume: fn() -> Resume) {
+        while self.started && !self.done {
+            match self.resume_inner(resume()) {
+                Some(GeneratorState::Yielded(item)) => self.release([item]),
+                Some(GeneratorState::Complete(value)) => self.return_value = Some(value),
+                // Panicked; the future is gone
+                None => break,
+            }
+        }
+        self.future = None;
+        self.run_cancel_hooks();
+    }
+
+    // What dropping does after the drop policy: release queued items,
+    // then run the on_cancel callbacks
+    fn cleanup(&mut self) {
+        let items = self.shared.with(|slot| slot.take_items());
+        self.release(items);
+        self.run_cancel_hooks();
+    }
+
+    // Pass items the consumer will never see to the
+    // set_on_unconsumed callback, if any
+    fn release(&mut self, items: impl IntoIterator<Item = Item>) {
+        let hook = self.shared.with(|slot| slot.on_unconsumed.take());
+        if let Some(mut hook) = hook {
+            items.into_iter().for_each(&mut hook);
+            self.shared.with(|slot| slot.on_unconsumed = Some(hook));
+        }
+    }
+
+    // Run the on_cancel callbacks. Completing clears them, so this
+    // does nothing after the async function returns.
+    fn run_cancel_hooks(&mut self) {
+        let hooks = self
+            .shared
+            .with(|slot| core::mem::take(&mut slot.on_cancel));
+        for hook in hooks {
+            hook();
+        }
+    }
+
+    // Move everything into a generator whose future is `f(future)`.
+    // `map_return` converts a return value the generator already has.
+    fn map_future<Fut2>(
+        mut self,
+        f: impl FnOnce(Fut) -> Fut2,
+        map_return: impl FnOnce(Fut::Output) -> Fut2::Output,
+    ) -> Generator<Item, Fut2, Resume, S>
+    where
+        Fut2: Future + Unpin,
+    {
+        Generator {
+            shared: self.shared.clone(),
+            future: self.future.take().map(f),
+            started: self.started,
+            done: self.done,
+            polls: self.polls,
+            idle_polls: self.idle_polls,
+            poisoned: self.poisoned,
+            wake_flag: self.wake_flag.take(),
+            driver: self.driver.take(),
+            return_value: self.return_value.take().map(map_return),
+            on_drop: self.on_drop.take().map(|(_, policy, resume)| {
+                (Generator::drop_with as fn(&mut _, _, _), policy, resume)
+            }),
+            cleanup: self
+                .cleanup
+                .take()
+                .map(|_| Generator::cleanup as fn(&mut _)),
+            #[cfg(feature = "std")]
+            catch_panics: self.catch_panics,
+            #[cfg(feature = "std")]
+            panic: Lock::new(shared::get_mut(&mut self.panic).take()),
+            item: PhantomData,
+        }
+    }
+
+    /// Let the async function await any future; `driver` drives
+    /// them. See [generate_with_driver].
+    pub fn set_driver(&mut self, driver: impl Driver + Send + Sync + 'static) {
+        self.driver = Some(Box::new(driver));
+    }
+
+    /// Choose whether to catch panics from the async function; see
+    /// [generate_catching].
+    #[cfg(feature = "std")]
+    pub fn set_catch_panics(&mut self, catch_panics: bool) {
+        self.catch_panics = catch_panics;
+    }
+
+    /// The payload of a caught panic from the async function
+    #[cfg(feature = "std")]
+    pub fn panic_payload(&mut self) -> Option<&(dyn Any + Send)> {
+        shared::get_mut(&mut self.panic).as_deref()
+    }
+
+    /// Move the payload of a caught panic out of the generator, e.g.
+    /// to pass it to [std::panic::resume_unwind].
+    #[cfg(feature = "std")]
+    pub fn take_panic_payload(&mut self) -> Option<Box<dyn Any + Send>> {
+        shared::get_mut(&mut self.panic).take()
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Poll the async function once with the caller's [Context], so
+    /// it may await any future, not just [Communication::yield_].
+    ///
+    /// `Pending` means it's waiting on such a future, which will wake
+    /// `cx`. Otherwise this is like [Iterator::next]. It suits event
+    /// loops and executors which bring their own wakers.
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// let mut generator = generate(|co| async move {
+    ///     co.yield_(1).await;
+    /// });
+    /// let mut cx = Context::from_waker(Waker::noop());
+    ///
+    /// assert_eq!(generator.poll_next(&mut cx), Poll::Ready(Some(1)));
+    /// assert_eq!(generator.poll_next(&mut cx), Poll::Ready(None));
+    /// ```
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        self.poll_resume(Some(Resume::default()), cx).map(|state| {
+            match state.map_or_else(error::misuse, Some)? {
+                GeneratorState::Yielded(item) => Some(item),
+                GeneratorState::Complete(value) => {
+                    self.return_value = Some(value);
+                    None
+                }
+            }
+        })
+    }
+
+    /// Await the next item from async code. This is
+    /// [Generator::poll_next] as a future, so the async function may
+    /// await anything the caller's executor can drive.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// async fn total(mut numbers: gen::LocalBoxGenerator<'_, i32>) -> i32 {
+    ///     let mut total = 0;
+    ///     while let Some(n) = numbers.async_next().await {
+    ///         total += n;
+    ///     }
+    ///     total
+    /// }
+    ///
+    /// let numbers = generate(|co| async move { co.yield_all(1..=4).await }).boxed_local();
+    /// let mut answer = generate(|co| async move { co.yield_(total(numbers).await).await });
+    /// assert_eq!(answer.next(), Some(10));
+    /// ```
+    pub async fn async_next(&mut self) -> Option<Item> {
+        core::future::poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Run the async function until it yields an item or returns.
+    ///
+    /// This is [Generator::resume_with] with `Resume::default()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning [GeneratorState::Complete].
+    pub fn resume(&mut self) -> GeneratorState<Item, Fut::Output> {
+        self.resume_with(Resume::default())
+    }
+
+    /// Tell the async function that iteration is over and let it
+    /// clean up.
+    ///
+    /// The waiting `yield_` resolves to `Resume::default()` and
+    /// [Communication::is_closed] becomes `true`. The async function
+    /// runs until it returns; its return value is then available from
+    /// [Generator::return_value]. If it yields again instead, the item
+    /// and the async function are dropped. Items still queued, e.g. by
+    /// [generate_buffered], are dropped first. Dropped items go to
+    /// [Generator::set_on_unconsumed]'s callback, if any. A generator that hasn't
+    /// started is dropped without running.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut generator = generate(|co| async move {
+    ///     let mut sent = 0;
+    ///     while !co.is_closed() {
+    ///         co.yield_(sent).await;
+    ///         sent += 1;
+    ///     }
+    ///     sent
+    /// });
+    ///
+    /// assert_eq!(generator.next(), Some(0));
+    /// assert_eq!(generator.next(), Some(1));
+    /// generator.close();
+    /// assert_eq!(generator.next(), None);
+    /// assert_eq!(generator.return_value(), Some(&2));
+    /// ```
+    pub fn close(&mut self) {
+        self.close_with(Resume::default);
+    }
+
+    /// Choose what dropping the generator does to an unfinished
+    /// async function.
+    pub fn set_drop_policy(&mut self, policy: DropPolicy) {
+        self.on_drop = match policy {
+            DropPolicy::Drop => None,
+            _ => Some((Self::drop_with, policy, Resume::default)),
+        };
+    }
+}
+
+// Records whether it was woken. Generator's synchronous driver uses
+// this to tell whether polling again could make progress.
+struct WakeFlag(AtomicBool);
+
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
     }
 }
 
+// Generator doesn't point to itself, and never pins `Fut`;
+// that's already a pinned pointer
+impl<Item, Fut: Future, Resume, S> Unpin for Generator<Item, Fut, Resume, S> {}
+
+impl<Item, Fut: Future, Resume, S> Drop for Generator<Item, Fut, Resume, S> {
+    fn drop(&mut self) {
+        if let Some((on_drop, policy, resume)) = self.on_drop.take() {
+            on_drop(self, policy, resume);
+        }
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup(self);
+        }
+    }
+}
+
+/// What dropping a [Generator] does to an unfinished async function
+///
+/// See [Generator::set_drop_policy].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DropPolicy {
+    /// Drop the async function where it's suspended. Code after
+    /// the waiting `yield_` never runs.
+    #[default]
+    Drop,
+
+    /// [Generator::close] the generator.
+    Close,
+
+    /// Keep resuming the async function until it returns, discarding
+    /// the rest of its items. Code after the waiting `yield_`, such as
+    /// flushing or committing, runs even if the consumer stopped early.
+    /// An async function that never returns makes dropping hang.
+    Complete,
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> Iterator
+    for Generator<Item, Fut, Resume, S>
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.send(Resume::default())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => (0, Some(0)),
+            false => self.shared.with(|slot| slot.size_hint()),
+        }
+    }
+}
+
+// Once finished, a generator never polls the async function again
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    core::iter::FusedIterator for Generator<Item, Fut, Resume, S>
+{
+}
+
+impl<Item, Fut: Future + Unpin, T, E, S: SharedState<Item, Result<T, E>>>
+    Generator<Item, Fut, Result<T, E>, S>
+{
+    /// Resolve the waiting `yield_` to `Err(err)`, then run the async
+    /// function until it yields an item or returns.
+    ///
+    /// This lets the async function react to consumer-side failures.
+    /// Resume normally with `send(Ok(..))`.
+    ///
+    /// ```
+    /// use gen::generate_bidi;
+    ///
+    /// let mut generator = generate_bidi(|co| async move {
+    ///     let mut attempts = 1;
+    ///     while let Err(e) = co.yield_(attempts).await {
+    ///         println!("retrying after {e}");
+    ///         attempts += 1;
+    ///     }
+    ///     attempts
+    /// });
+    ///
+    /// assert_eq!(generator.send(Ok(())), Some(1));
+    /// assert_eq!(generator.throw("timeout"), Some(2));
+    /// assert_eq!(generator.send(Ok(())), None);
+    /// assert_eq!(generator.into_return(), Some(2));
+    /// ```
+    pub fn throw(&mut self, err: E) -> Option<Item> {
+        self.send(Err(err))
+    }
+}
+
+/// Polls the async function with the caller's [Context], so it may
+/// await any future, not just [Communication::yield_].
+#[cfg(feature = "nightly")]
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    core::async_iter::AsyncIterator for Generator<Item, Fut, Resume, S>
+{
+    type Item = Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Generator::poll_next(self.get_mut(), cx)
+    }
+}
+
+/// Resuming passes the argument to the waiting `yield_`, like
+/// [Generator::resume_with].
+#[cfg(feature = "nightly")]
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>> core::ops::Coroutine<Resume>
+    for Generator<Item, Fut, Resume, S>
+{
+    type Yield = Item;
+    type Return = Fut::Output;
+
+    fn resume(
+        self: Pin<&mut Self>,
+        arg: Resume,
+    ) -> core::ops::CoroutineState<Self::Yield, Self::Return> {
+        match self.get_mut().resume_with(arg) {
+            GeneratorState::Yielded(item) => core::ops::CoroutineState::Yielded(item),
+            GeneratorState::Complete(value) => core::ops::CoroutineState::Complete(value),
+        }
+    }
+}
+
+/// Result of [Generator::resume]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GeneratorState<Yield, Return> {
+    /// The async function yielded an item.
+    Yielded(Yield),
+
+    /// The async function returned.
+    Complete(Return),
+}
+
+/// Result of [Generator::state]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Nothing has resumed the generator yet.
+    NotStarted,
+
+    /// The async function is waiting for the generator to resume it.
+    Suspended,
+
+    /// The async function returned, or the generator was closed.
+    Completed,
+
+    /// The async function panicked.
+    Poisoned,
+}
+
 /// Communicate with [Generator]
 ///
 /// The function passed to `generate` receives this as an
@@ -115,41 +1139,214 @@ impl<Item, Fut: Future<Output = ()>> Iterator for Generator<Item, Fut> {
 /// This type could have also been named Coroutine, but
 /// I thought it better to reserve that name for the async
 /// function.
-pub struct Communication<Item>(SharedState<Item>);
+pub struct Communication<Item, Resume = (), S = SyncShared<Item, Resume>>(
+    S,
+    PhantomData<fn() -> (Item, Resume)>,
+);
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    fn new(shared: S) -> Self {
+        Self(shared, PhantomData)
+    }
 
-impl<Item> Communication<Item> {
     /// Pass a single value to [Generator]. `yield_` acts as
-    /// an async function.
-    pub fn yield_(&self, item: Item) -> YieldFuture<Item> {
+    /// an async function. It resolves to the value passed to
+    /// [Generator::send], or `()` for [generate].
+    pub fn yield_(&self, item: Item) -> YieldFuture<'_, Item, Resume, S> {
         YieldFuture {
             shared: &self.0,
             value: Some(item),
+            ticket: 0,
+            resume: PhantomData,
         }
     }
+
+    /// Whether [Generator::close] was called. Once this is `true`,
+    /// the async function should clean up and return instead of
+    /// yielding again.
+    pub fn is_closed(&self) -> bool {
+        self.0.with(|slot| slot.closed)
+    }
+
+    /// Like [Communication::yield_], but only builds the item if the
+    /// consumer will receive it. After [Generator::close], this skips
+    /// `f` and resolves to `None`.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     co.yield_with(|| "cheap".to_string()).await;
+    ///     let rendered = co.yield_with(|| unreachable!("expensive")).await;
+    ///     assert_eq!(rendered, None);
+    /// });
+    ///
+    /// assert_eq!(iter.next().as_deref(), Some("cheap"));
+    /// iter.close();
+    /// ```
+    pub async fn yield_with(&self, f: impl FnOnce() -> Item) -> Option<Resume> {
+        if self.is_closed() {
+            return None;
+        }
+        Some(self.yield_(f()).await)
+    }
+
+    /// Pass each of `items` to [Generator], in order. This discards
+    /// the values `yield_` resolves to.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let iter = generate(|co| async move {
+    ///     co.yield_(0).await;
+    ///     co.yield_all(1..4).await;
+    ///     co.yield_all(vec![10, 20]).await;
+    /// });
+    ///
+    /// assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2, 3, 10, 20]);
+    /// ```
+    pub async fn yield_all(&self, items: impl IntoIterator<Item = Item>) {
+        for item in items {
+            self.yield_(item).await;
+        }
+    }
+
+    /// Run a sub-generator on this `Communication`, returning its
+    /// return value. Everything it yields passes straight through.
+    ///
+    /// `f` receives this `Communication`, so helpers written as
+    /// `async fn(&Communication<..>) -> R` compose directly.
+    ///
+    /// ```
+    /// use gen::{generate, Communication};
+    ///
+    /// // Yields the digits of `n`, returning how many there were
+    /// async fn digits(co: &Communication<u32>, n: u32) -> usize {
+    ///     let s = n.to_string();
+    ///     for c in s.chars() {
+    ///         co.yield_(c.to_digit(10).unwrap()).await;
+    ///     }
+    ///     s.len()
+    /// }
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     let a = co.yield_from(|co| digits(co, 42)).await;
+    ///     let b = co.yield_from(|co| digits(co, 7)).await;
+    ///     a + b
+    /// });
+    ///
+    /// assert_eq!(iter.by_ref().collect::<Vec<_>>(), [4, 2, 7]);
+    /// assert_eq!(iter.return_value(), Some(&3));
+    /// ```
+    pub async fn yield_from<'a, F, Fut>(&'a self, f: F) -> Fut::Output
+    where
+        F: FnOnce(&'a Self) -> Fut,
+        Fut: Future,
+    {
+        f(self).await
+    }
+
+    /// Pass each item of an async iterator to [Generator], in order.
+    /// This discards the values `yield_` resolves to.
+    ///
+    /// Awaiting the async iterator may need a [Driver].
+    #[cfg(feature = "nightly")]
+    pub async fn forward(&self, iter: impl core::async_iter::AsyncIterator<Item = Item>) {
+        let mut iter = core::pin::pin!(iter);
+        while let Some(item) = core::future::poll_fn(|cx| iter.as_mut().poll_next(cx)).await {
+            self.yield_(item).await;
+        }
+    }
+}
+
+/// Clones talk to the same [Generator]. This lets the async function
+/// poll several futures which yield, e.g. with a `join!` macro. Items
+/// reach [Generator] in the order their `yield_` was first polled, and
+/// each `yield_` resolves once [Generator] passes on the item after
+/// its own.
+///
+/// ```
+/// use gen::generate;
+/// use std::{
+///     future::{poll_fn, Future},
+///     pin::pin,
+///     task::Poll,
+/// };
+///
+/// let iter = generate(|co| async move {
+///     let evens = co.clone();
+///     let mut a = pin!(async move {
+///         for i in (0..6).step_by(2) {
+///             evens.yield_(i).await;
+///         }
+///     });
+///     let mut b = pin!(async move {
+///         co.yield_all([1, 3, 5]).await;
+///     });
+///
+///     // A minimal join!
+///     let (mut a_done, mut b_done) = (false, false);
+///     poll_fn(|cx| {
+///         a_done = a_done || a.as_mut().poll(cx).is_ready();
+///         b_done = b_done || b.as_mut().poll(cx).is_ready();
+///         if a_done && b_done {
+///             Poll::Ready(())
+///         } else {
+///             Poll::Pending
+///         }
+///     })
+///     .await;
+/// });
+///
+/// assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5]);
+/// ```
+impl<Item, Resume, S: SharedState<Item, Resume>> Clone for Communication<Item, Resume, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
 }
 
 /// Future returned by [Communication::yield_]
-pub struct YieldFuture<'a, Item> {
-    shared: &'a Mutex<Option<Item>>,
+pub struct YieldFuture<'a, Item, Resume = (), S = SyncShared<Item, Resume>> {
+    shared: &'a S,
     value: Option<Item>,
+    ticket: u64,
+    resume: PhantomData<fn() -> Resume>,
 }
 
 // YieldFuture doesn't point to itself
-impl<'a, Item> Unpin for YieldFuture<'a, Item> {}
+impl<'a, Item, Resume, S> Unpin for YieldFuture<'a, Item, Resume, S> {}
 
-impl<'a, Item> Future for YieldFuture<'a, Item> {
-    type Output = ();
+impl<'a, Item, Resume, S: SharedState<Item, Resume>> Future for YieldFuture<'a, Item, Resume, S> {
+    type Output = Resume;
 
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
-        let mut lock = this.shared.lock().unwrap();
-        if let Some(item) = this.value.take() {
-            lock.replace(item);
-            Poll::Pending
-        } else if lock.is_some() {
-            panic!("YieldFuture used within incorrect executor")
-        } else {
-            Poll::Ready(())
-        }
+        this.shared.with(|slot| {
+            if let Some(item) = this.value.take() {
+                this.ticket = slot.push(item);
+                match slot.buffer {
+                    Some((n, resume)) if slot.items.len() < n => Poll::Ready(resume()),
+                    _ => Poll::Pending,
+                }
+            } else if this.ticket >= slot.delivered {
+                // Generator hasn't taken the item yet
+                Poll::Pending
+            } else if let Some((_, resume)) = slot.buffer {
+                Poll::Ready(resume())
+            } else {
+                match slot.resume.take_if(|(ticket, _)| *ticket == this.ticket) {
+                    Some((_, resume)) => Poll::Ready(resume),
+                    None => match slot.resolved_through {
+                        Some((through, resume)) if this.ticket < through => Poll::Ready(resume()),
+                        _ if slot.driven => {
+                            slot.error = Some(crate::Error::WrongExecutor);
+                            Poll::Pending
+                        }
+                        _ => panic!("{}", crate::Error::WrongExecutor),
+                    },
+                }
+            }
+        })
     }
 }
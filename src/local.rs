@@ -0,0 +1,49 @@
+//! Single-threaded generators.
+//!
+//! These mirror the crate root's API, but share state through
+//! `Rc<RefCell<..>>` instead of `Arc<Mutex<..>>`. This avoids locking
+//! and atomic refcounting, at the cost of the generator being `!Send`.
+//!
+//! ```
+//! use gen::local::generate;
+//!
+//! let iter = generate(|co| async move {
+//!     for i in 0..4 {
+//!         co.yield_(i).await;
+//!     }
+//! });
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2, 3]);
+//! ```
+
+use crate::shared::Slot;
+use alloc::{boxed::Box, rc::Rc};
+use core::{cell::RefCell, future::Future, pin::Pin};
+
+pub(crate) type LocalShared<Item, Resume> = Rc<RefCell<Slot<Item, Resume>>>;
+
+/// A single-threaded [crate::Generator]
+pub type Generator<Item, Fut, Resume = ()> =
+    crate::Generator<Item, Fut, Resume, LocalShared<Item, Resume>>;
+
+/// A single-threaded [crate::Communication]
+pub type Communication<Item, Resume = ()> =
+    crate::Communication<Item, Resume, LocalShared<Item, Resume>>;
+
+/// Single-threaded version of [crate::generate]
+pub fn generate<Item, F, Fut>(f: F) -> Generator<Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    generate_bidi(f)
+}
+
+/// Single-threaded version of [crate::generate_bidi]
+pub fn generate_bidi<Item, Resume, F, Fut>(f: F) -> Generator<Item, Pin<Box<Fut>>, Resume>
+where
+    F: FnOnce(Communication<Item, Resume>) -> Fut,
+    Fut: Future,
+{
+    Generator::new_boxed(Default::default(), f)
+}
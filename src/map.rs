@@ -0,0 +1,136 @@
+// Transforming a generator's items or return value without losing
+// the Generator API.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use core::{
+    future::Future,
+    iter::FusedIterator,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Like [Iterator::map], but keeps access to the generator, so
+    /// its return value, state, and [Generator::close] stay reachable.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut lengths = generate(|co| async move {
+    ///     co.yield_all(["one", "three"]).await;
+    ///     "done"
+    /// })
+    /// .map_items(str::len);
+    ///
+    /// assert_eq!(lengths.by_ref().collect::<Vec<_>>(), [3, 5]);
+    /// assert_eq!(lengths.generator().return_value(), Some(&"done"));
+    /// ```
+    pub fn map_items<B, F: FnMut(Item) -> B>(self, f: F) -> MapItems<Item, Fut, F, Resume, S> {
+        MapItems { generator: self, f }
+    }
+
+    /// Transform the async function's return value. The result is
+    /// still a [Generator], with the same items and settings.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     co.yield_(1).await;
+    ///     "7"
+    /// })
+    /// .map_return(|s| s.parse::<i32>());
+    ///
+    /// assert_eq!(iter.next(), Some(1));
+    /// assert_eq!(iter.next(), None);
+    /// assert_eq!(iter.return_value(), Some(&Ok(7)));
+    /// ```
+    pub fn map_return<R, F>(self, f: F) -> Generator<Item, MapReturn<Fut, F>, Resume, S>
+    where
+        F: FnOnce(Fut::Output) -> R + Unpin,
+    {
+        // The return value exists already, or the future produces it;
+        // whichever it is gets `f`.
+        let (later, now) = match self.return_value {
+            None => (Some(f), None),
+            Some(_) => (None, Some(f)),
+        };
+        self.map_future(
+            |future| MapReturn { future, f: later },
+            |value| (now.expect("return value without a mapping"))(value),
+        )
+    }
+}
+
+/// A [Generator] whose items pass through a function
+///
+/// [Generator::map_items] returns this.
+pub struct MapItems<Item, Fut: Future, F, Resume = (), S = SyncShared<Item, Resume>> {
+    generator: Generator<Item, Fut, Resume, S>,
+    f: F,
+}
+
+impl<Item, Fut: Future, F, Resume, S> MapItems<Item, Fut, F, Resume, S> {
+    /// The underlying [Generator]
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.generator
+    }
+
+    /// The underlying [Generator]
+    pub fn generator_mut(&mut self) -> &mut Generator<Item, Fut, Resume, S> {
+        &mut self.generator
+    }
+
+    /// Consume this, returning the underlying [Generator]
+    pub fn into_generator(self) -> Generator<Item, Fut, Resume, S> {
+        self.generator
+    }
+}
+
+impl<Item, Fut, F, B, Resume, S> Iterator for MapItems<Item, Fut, F, Resume, S>
+where
+    Fut: Future + Unpin,
+    F: FnMut(Item) -> B,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        self.generator.next().map(&mut self.f)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.generator.size_hint()
+    }
+}
+
+impl<Item, Fut, F, B, Resume, S> FusedIterator for MapItems<Item, Fut, F, Resume, S>
+where
+    Fut: Future + Unpin,
+    F: FnMut(Item) -> B,
+    Resume: Default,
+    S: SharedState<Item, Resume>,
+{
+}
+
+/// A future whose output passes through a function
+///
+/// This is the future of the [Generator] [Generator::map_return]
+/// returns.
+pub struct MapReturn<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut: Future + Unpin, F: FnOnce(Fut::Output) -> R + Unpin, R> Future for MapReturn<Fut, F> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let value = core::task::ready!(Pin::new(&mut self.future).poll(cx));
+        let f = self.f.take().expect("MapReturn polled after completion");
+        Poll::Ready(f(value))
+    }
+}
@@ -0,0 +1,112 @@
+// Iterating a generator's items more than once.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use alloc::vec::Vec;
+use core::{future::Future, iter::FusedIterator};
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Cache every item so they can be iterated more than once.
+    ///
+    /// Each [Memoized::iter] replays the cached items, then resumes
+    /// the async function for more. The async function runs once.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut squares = generate(|co| async move {
+    ///     for i in 1..=4 {
+    ///         co.yield_(i * i).await;
+    ///     }
+    /// })
+    /// .memoize();
+    ///
+    /// assert_eq!(squares.iter().take(2).collect::<Vec<_>>(), [1, 4]);
+    /// assert_eq!(squares.cached(), [1, 4]);
+    /// assert_eq!(squares.iter().collect::<Vec<_>>(), [1, 4, 9, 16]);
+    /// assert_eq!(squares.iter().sum::<i32>(), 30);
+    /// ```
+    pub fn memoize(self) -> Memoized<Item, Fut, Resume, S> {
+        Memoized {
+            generator: self,
+            cache: Vec::new(),
+        }
+    }
+}
+
+/// A [Generator] which remembers its items
+///
+/// [Generator::memoize] returns this.
+pub struct Memoized<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    generator: Generator<Item, Fut, Resume, S>,
+    cache: Vec<Item>,
+}
+
+impl<Item, Fut: Future, Resume, S> Memoized<Item, Fut, Resume, S> {
+    /// The items yielded so far
+    pub fn cached(&self) -> &[Item] {
+        &self.cache
+    }
+
+    /// The underlying [Generator]
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.generator
+    }
+
+    /// Consume this, returning the underlying [Generator] and the
+    /// items it has yielded so far.
+    pub fn into_parts(self) -> (Generator<Item, Fut, Resume, S>, Vec<Item>) {
+        (self.generator, self.cache)
+    }
+}
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Memoized<Item, Fut, Resume, S>
+{
+    /// Iterate from the first item
+    pub fn iter(&mut self) -> Replay<'_, Item, Fut, Resume, S> {
+        Replay {
+            memoized: self,
+            position: 0,
+        }
+    }
+}
+
+/// One pass over a [Memoized] generator's items
+///
+/// [Memoized::iter] returns this.
+pub struct Replay<'a, Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    memoized: &'a mut Memoized<Item, Fut, Resume, S>,
+    position: usize,
+}
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> Iterator
+    for Replay<'_, Item, Fut, Resume, S>
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        let memoized = &mut *self.memoized;
+        if self.position == memoized.cache.len() {
+            let item = memoized.generator.next()?;
+            memoized.cache.push(item);
+        }
+        self.position += 1;
+        Some(memoized.cache[self.position - 1].clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let cached = self.memoized.cache.len() - self.position;
+        let (lower, upper) = self.memoized.generator.size_hint();
+        (
+            lower.saturating_add(cached),
+            upper.and_then(|upper| upper.checked_add(cached)),
+        )
+    }
+}
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> FusedIterator
+    for Replay<'_, Item, Fut, Resume, S>
+{
+}
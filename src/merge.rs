@@ -0,0 +1,174 @@
+// Combining several generators into one.
+
+use alloc::{
+    collections::{BinaryHeap, VecDeque},
+    vec::Vec,
+};
+use core::{cmp::Ordering, iter::FusedIterator};
+
+/// Interleave several generators, taking one item from each in turn.
+///
+/// A generator which finishes drops out; the rest continue. Nothing
+/// is collected ahead of time. Any [Iterator] works, not only
+/// [crate::Generator].
+///
+/// ```
+/// use gen::{generate, merge};
+///
+/// let count = |n: i32| {
+///     generate(move |co| async move {
+///         for i in 0..n {
+///             co.yield_(i * 10 + n).await;
+///         }
+///     })
+/// };
+///
+/// let merged = merge([count(1), count(3), count(2)]);
+/// assert_eq!(merged.collect::<Vec<_>>(), [1, 3, 2, 13, 12, 23]);
+/// ```
+pub fn merge<I>(generators: I) -> Merge<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Iterator,
+{
+    Merge(generators.into_iter().collect())
+}
+
+/// Round-robin interleaving of generators
+///
+/// [merge] returns this.
+pub struct Merge<G>(VecDeque<G>);
+
+impl<G: Iterator> Iterator for Merge<G> {
+    type Item = G::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(mut generator) = self.0.pop_front() {
+            if let Some(item) = generator.next() {
+                self.0.push_back(generator);
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0
+            .iter()
+            .map(Iterator::size_hint)
+            .fold((0, Some(0)), |(lower, upper), (l, u)| {
+                (
+                    lower.saturating_add(l),
+                    upper.zip(u).and_then(|(a, b)| a.checked_add(b)),
+                )
+            })
+    }
+}
+
+impl<G: Iterator> FusedIterator for Merge<G> {}
+
+/// Merge generators which each yield items sorted by `key` into one
+/// sorted iterator.
+///
+/// Each step takes the smallest next item, holding one pending item
+/// per generator in a binary heap. Ties go to the earlier generator.
+///
+/// ```
+/// use gen::{generate, merge_sorted_by_key};
+///
+/// let log = |entries: Vec<(u32, &'static str)>| {
+///     generate(move |co| async move {
+///         co.yield_all(entries).await;
+///     })
+///     .boxed_local()
+/// };
+///
+/// let merged = merge_sorted_by_key(
+///     [
+///         log(vec![(1, "boot"), (5, "ready")]),
+///         log(vec![(2, "disk"), (3, "net"), (9, "idle")]),
+///     ],
+///     |(time, _)| *time,
+/// );
+///
+/// let messages: Vec<_> = merged.map(|(_, message)| message).collect();
+/// assert_eq!(messages, ["boot", "disk", "net", "ready", "idle"]);
+/// ```
+pub fn merge_sorted_by_key<I, K, F>(generators: I, key: F) -> MergeSorted<I::Item, K, F>
+where
+    I: IntoIterator,
+    I::Item: Iterator,
+    K: Ord,
+    F: FnMut(&<I::Item as Iterator>::Item) -> K,
+{
+    MergeSorted {
+        generators: generators.into_iter().collect(),
+        heap: BinaryHeap::new(),
+        key,
+        started: false,
+    }
+}
+
+/// Sorted merge of sorted generators
+///
+/// [merge_sorted_by_key] returns this.
+pub struct MergeSorted<G: Iterator, K, F> {
+    generators: Vec<G>,
+    heap: BinaryHeap<Head<K, G::Item>>,
+    key: F,
+    started: bool,
+}
+
+// The next item of generator `source`. The heap is a max-heap, so
+// this orders smallest key, then source, first.
+struct Head<K, Item> {
+    key: K,
+    source: usize,
+    item: Item,
+}
+
+impl<K: Ord, Item> Ord for Head<K, Item> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&other.key, other.source).cmp(&(&self.key, self.source))
+    }
+}
+
+impl<K: Ord, Item> PartialOrd for Head<K, Item> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, Item> PartialEq for Head<K, Item> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, Item> Eq for Head<K, Item> {}
+
+impl<G: Iterator, K: Ord, F: FnMut(&G::Item) -> K> MergeSorted<G, K, F> {
+    // Queue the next item of generator `source`, if any
+    fn advance(&mut self, source: usize) {
+        if let Some(item) = self.generators[source].next() {
+            let key = (self.key)(&item);
+            self.heap.push(Head { key, source, item });
+        }
+    }
+}
+
+impl<G: Iterator, K: Ord, F: FnMut(&G::Item) -> K> Iterator for MergeSorted<G, K, F> {
+    type Item = G::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            for source in 0..self.generators.len() {
+                self.advance(source);
+            }
+        }
+        let Head { source, item, .. } = self.heap.pop()?;
+        self.advance(source);
+        Some(item)
+    }
+}
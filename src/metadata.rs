@@ -0,0 +1,39 @@
+// Out-of-band annotations from the async function.
+
+use crate::{shared::SharedState, Communication, Generator};
+use alloc::{collections::BTreeMap, string::String};
+use core::future::Future;
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Record `value` under `key` in the generator's metadata,
+    /// replacing any earlier value. [Generator::metadata] reads it
+    /// during and after iteration.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut rows = generate(|co| async move {
+    ///     co.annotate("source", "users.csv");
+    ///     co.annotate("schema", "v2");
+    ///     co.yield_all(["alice", "bob"]).await;
+    ///     co.annotate("warnings", "1 row skipped");
+    /// });
+    ///
+    /// assert_eq!(rows.next(), Some("alice"));
+    /// assert_eq!(rows.metadata()["source"], "users.csv");
+    /// rows.by_ref().for_each(drop);
+    /// assert_eq!(rows.metadata().len(), 3);
+    /// ```
+    pub fn annotate(&self, key: impl Into<String>, value: impl Into<String>) {
+        let (key, value) = (key.into(), value.into());
+        self.0.with(|slot| slot.metadata.insert(key, value));
+    }
+}
+
+impl<Item, Fut: Future, Resume, S: SharedState<Item, Resume>> Generator<Item, Fut, Resume, S> {
+    /// A copy of what the async function has recorded with
+    /// [Communication::annotate]
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        self.shared.with(|slot| slot.metadata.clone())
+    }
+}
@@ -0,0 +1,72 @@
+// Building the future outside generate.
+
+use crate::{shared::SharedState, Communication, Generator, SyncShared};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+impl<Item, Resume> Communication<Item, Resume> {
+    /// A `Communication`, and the means to attach the future which
+    /// uses it to a [Generator].
+    ///
+    /// This suits futures which take more than a `Communication` to
+    /// build, e.g. async functions with borrowed arguments.
+    ///
+    /// ```
+    /// use gen::Communication;
+    ///
+    /// async fn repeat(co: Communication<char>, c: char, times: &usize) {
+    ///     for _ in 0..*times {
+    ///         co.yield_(c).await;
+    ///     }
+    /// }
+    ///
+    /// let times = 3;
+    /// let (co, attach) = Communication::<char>::pair();
+    /// let iter = attach.into_generator(repeat(co, 'x', &times));
+    /// assert_eq!(iter.collect::<String>(), "xxx");
+    /// ```
+    pub fn pair() -> (Self, Attach<Item, Resume>) {
+        let shared = SyncShared::default();
+        (Communication::new(shared.clone()), Attach(shared))
+    }
+}
+
+/// The [Generator] end of [Communication::pair]
+pub struct Attach<Item, Resume = ()>(SyncShared<Item, Resume>);
+
+impl<Item, Resume> Attach<Item, Resume> {
+    /// Make the generator which runs `future`. `future` should use the
+    /// `Communication` from the same [Communication::pair].
+    pub fn into_generator<Fut: Future>(
+        self,
+        future: Fut,
+    ) -> Generator<Item, Pin<Box<Fut>>, Resume> {
+        Generator::new(self.0, Some(Box::pin(future)))
+    }
+}
+
+impl<Item, Fut: Future, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Pin<Box<Fut>>, Resume, S>
+{
+    /// Make the generator which runs `future`, an already-built
+    /// future which yields through a clone of `co`.
+    ///
+    /// Futures can then come from factories or caches, or be built
+    /// long before iteration starts.
+    ///
+    /// ```
+    /// use gen::{Communication, Generator};
+    ///
+    /// fn job(co: Communication<u32>, base: u32) -> impl std::future::Future<Output = ()> {
+    ///     async move { co.yield_all([base, base + 1]).await }
+    /// }
+    ///
+    /// let (co, _) = Communication::<u32>::pair();
+    /// let future = job(co.clone(), 10);
+    /// let iter = Generator::from_future(co, future);
+    /// assert_eq!(iter.collect::<Vec<_>>(), [10, 11]);
+    /// ```
+    pub fn from_future(co: Communication<Item, Resume, S>, future: Fut) -> Self {
+        Generator::new(co.0, Some(Box::pin(future)))
+    }
+}
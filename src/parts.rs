@@ -0,0 +1,88 @@
+// Taking a generator apart, for code which polls the future itself.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use core::{future::Future, marker::PhantomData};
+
+/// The state a [Generator] shares with its [crate::Communication]
+///
+/// [Generator::into_parts] returns this with the future. Code which
+/// polls the future itself uses it to collect yielded items and pass
+/// resume values, as [Generator] would.
+///
+/// ```
+/// use gen::generate_bidi;
+/// use std::{
+///     future::Future,
+///     task::{Context, Waker},
+/// };
+///
+/// let generator = generate_bidi(|co| async move {
+///     let x = co.yield_('a').await;
+///     co.yield_(x).await;
+/// });
+/// let (future, shared) = generator.into_parts();
+/// let mut future = future.unwrap();
+/// let mut cx = Context::from_waker(Waker::noop());
+///
+/// assert!(future.as_mut().poll(&mut cx).is_pending());
+/// assert_eq!(shared.take_item(), Some('a'));
+/// shared.set_resume('b');
+/// assert!(future.as_mut().poll(&mut cx).is_pending());
+/// assert_eq!(shared.take_item(), Some('b'));
+/// ```
+pub struct Shared<Item, Resume = (), S = SyncShared<Item, Resume>>(
+    S,
+    PhantomData<fn() -> (Item, Resume)>,
+);
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Shared<Item, Resume, S> {
+    /// Take the next item yielded. After polling the future returns
+    /// `Pending`, this is `None` if it's waiting on something other
+    /// than `yield_`.
+    pub fn take_item(&self) -> Option<Item> {
+        self.0.with(|slot| {
+            let item = slot.pop()?;
+            slot.resume = None;
+            Some(item)
+        })
+    }
+
+    /// Pass `resume` to the `yield_` whose item was taken last. Poll
+    /// the future next so it receives it.
+    pub fn set_resume(&self, resume: Resume) {
+        self.0.with(|slot| {
+            if let Some(ticket) = slot.delivered.checked_sub(1) {
+                slot.resume = Some((ticket, resume));
+            }
+        });
+    }
+
+    /// Make [crate::Communication::is_closed] `true`.
+    pub fn close(&self) {
+        self.0.with(|slot| slot.closed = true);
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Take the generator apart. The future is `None` once the
+    /// generator has finished. The generator's settings, return value,
+    /// and drop policy are discarded. `on_cancel` callbacks and queued
+    /// items stay with the shared state.
+    pub fn into_parts(mut self) -> (Option<Fut>, Shared<Item, Resume, S>) {
+        self.on_drop = None;
+        self.cleanup = None;
+        self.shared.with(|slot| slot.driven = false);
+        (self.future.take(), Shared(self.shared.clone(), PhantomData))
+    }
+
+    /// Put a generator back together from [Generator::into_parts].
+    /// If `future` is `None`, the generator is finished.
+    pub fn from_parts(future: Option<Fut>, shared: Shared<Item, Resume, S>) -> Self {
+        let started = shared.0.with(|slot| slot.delivered > 0);
+        let mut generator = Generator::new(shared.0, future);
+        generator.started = started;
+        generator
+    }
+}
@@ -0,0 +1,103 @@
+// Looking ahead without losing the Generator.
+
+use crate::{shared::SharedState, Generator, SyncShared};
+use core::future::Future;
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Like [Iterator::peekable], but keeps access to the generator.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     co.yield_all([1, 2]).await;
+    ///     "end"
+    /// })
+    /// .peekable();
+    ///
+    /// assert_eq!(iter.peek(), Some(&1));
+    /// *iter.peek_mut().unwrap() = 10;
+    /// assert_eq!(iter.by_ref().collect::<Vec<_>>(), [10, 2]);
+    /// assert_eq!(iter.generator().return_value(), Some(&"end"));
+    /// ```
+    pub fn peekable(self) -> PeekableGenerator<Item, Fut, Resume, S> {
+        PeekableGenerator {
+            generator: self,
+            peeked: None,
+        }
+    }
+}
+
+/// A [Generator] which can look at its next item without consuming it
+///
+/// [Generator::peekable] returns this.
+pub struct PeekableGenerator<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    generator: Generator<Item, Fut, Resume, S>,
+    peeked: Option<Option<Item>>,
+}
+
+impl<Item, Fut: Future, Resume, S> PeekableGenerator<Item, Fut, Resume, S> {
+    /// The underlying [Generator]. It may have run one item ahead.
+    pub fn generator(&self) -> &Generator<Item, Fut, Resume, S> {
+        &self.generator
+    }
+
+    /// The underlying [Generator], e.g. to [Generator::close] it.
+    /// Items taken through it skip ahead of a peeked item.
+    pub fn generator_mut(&mut self) -> &mut Generator<Item, Fut, Resume, S> {
+        &mut self.generator
+    }
+
+    /// Consume this, returning the underlying [Generator] and the
+    /// peeked item, if any.
+    pub fn into_parts(self) -> (Generator<Item, Fut, Resume, S>, Option<Item>) {
+        (self.generator, self.peeked.flatten())
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    PeekableGenerator<Item, Fut, Resume, S>
+{
+    /// The next item, without consuming it
+    pub fn peek(&mut self) -> Option<&Item> {
+        self.peek_mut().map(|item| &*item)
+    }
+
+    /// The next item, without consuming it, for modification
+    pub fn peek_mut(&mut self) -> Option<&mut Item> {
+        let generator = &mut self.generator;
+        self.peeked.get_or_insert_with(|| generator.next()).as_mut()
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> Iterator
+    for PeekableGenerator<Item, Fut, Resume, S>
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.generator.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.generator.size_hint();
+        match self.peeked {
+            Some(None) => (0, Some(0)),
+            Some(Some(_)) => (
+                lower.saturating_add(1),
+                upper.and_then(|upper| upper.checked_add(1)),
+            ),
+            None => (lower, upper),
+        }
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    core::iter::FusedIterator for PeekableGenerator<Item, Fut, Resume, S>
+{
+}
@@ -0,0 +1,54 @@
+// Items which were yielded but never delivered.
+
+use crate::{shared::SharedState, Generator};
+use alloc::{boxed::Box, vec::Vec};
+use core::future::Future;
+
+impl<Item, Fut: Future + Unpin, Resume, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Run `f` on each item the consumer will never receive, instead
+    /// of dropping it silently.
+    ///
+    /// Those are items still queued (see [crate::generate_buffered]
+    /// and [crate::Communication::yield_chunk]) when the generator
+    /// closes or drops or its async function panics, items from
+    /// `yield_`s an unbuffered async function abandoned by returning,
+    /// and items yielded while [Generator::close] or
+    /// [crate::DropPolicy::Complete] finishes the async function.
+    ///
+    /// ```
+    /// use gen::generate_buffered;
+    /// use std::sync::mpsc;
+    ///
+    /// let (recycle, recycled) = mpsc::channel();
+    /// let mut buffers = generate_buffered(4, |co| async move {
+    ///     for i in 0..4 {
+    ///         co.yield_(vec![i; 16]).await;
+    ///     }
+    /// });
+    /// buffers.set_on_unconsumed(move |buffer| recycle.send(buffer).unwrap());
+    ///
+    /// assert_eq!(buffers.next().unwrap()[0], 0);
+    /// drop(buffers);
+    /// assert_eq!(recycled.iter().count(), 3);
+    /// ```
+    pub fn set_on_unconsumed(&mut self, f: impl FnMut(Item) + Send + 'static) {
+        self.shared
+            .with(|slot| slot.on_unconsumed = Some(Box::new(f)));
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Take the items the async function has queued but the consumer
+    /// hasn't received. They count as delivered: their `yield_`s, and
+    /// the one waiting on the item delivered before them, resolve to
+    /// `Resume::default()`, except that the next resume value goes to
+    /// the last one taken.
+    pub fn take_pending(&mut self) -> Vec<Item> {
+        self.shared
+            .with(|slot| slot.deliver_all(Resume::default).into())
+    }
+}
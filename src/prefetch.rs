@@ -0,0 +1,234 @@
+// Generators which produce on a background thread.
+
+use crate::{shared::SharedState, Communication, Generator, TimedOut};
+use std::{
+    future::Future,
+    io, panic,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Version of [crate::generate] which runs the async function on its
+/// own thread, so producing items overlaps with consuming them.
+///
+/// The thread runs up to `capacity` items ahead. It calls `f`, so the
+/// future itself needn't be `Send`. A panic in the async function
+/// resumes in [Iterator::next] once the items before it are consumed.
+///
+/// ```
+/// use gen::generate_prefetch;
+///
+/// let lines = generate_prefetch(16, |co| async move {
+///     for i in 0..100 {
+///         // Stands in for decoding or I/O
+///         co.yield_(format!("line {i}")).await;
+///     }
+/// });
+///
+/// assert_eq!(lines.map(|line| line.len()).sum::<usize>(), 690);
+/// ```
+pub fn generate_prefetch<Item, F, Fut>(capacity: usize, f: F) -> Prefetch<Item>
+where
+    Item: Send + 'static,
+    F: FnOnce(Communication<Item>) -> Fut + Send + 'static,
+    Fut: Future,
+{
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    let thread = thread::spawn(move || {
+        for item in crate::generate(f) {
+            if sender.send(item).is_err() {
+                // Prefetch dropped; the async function drops with
+                // the generator.
+                return;
+            }
+        }
+    });
+    Prefetch {
+        receiver: Some(receiver),
+        thread: Some(thread),
+        output: None,
+    }
+}
+
+impl<Item, Fut, Resume, S> Generator<Item, Fut, Resume, S>
+where
+    Item: Send + 'static,
+    Fut: Future + Unpin + Send + 'static,
+    Fut::Output: Send,
+    Resume: Default + Send + 'static,
+    S: SharedState<Item, Resume> + Send + 'static,
+{
+    /// Drive the generator on its own thread, sending its items to a
+    /// channel which holds up to `capacity` of them.
+    ///
+    /// [Prefetch::join] gives the async function's return value, or
+    /// `None` if it was called before the items ran out. Dropping the
+    /// result, or calling `join` early, stops the thread at its next
+    /// item and drops the generator, in line with its drop policy.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let mut channel = generate(|co| async move {
+    ///     co.yield_all(1..=3).await;
+    ///     "done"
+    /// })
+    /// .into_channel(1);
+    ///
+    /// assert_eq!(channel.by_ref().sum::<i32>(), 6);
+    /// assert_eq!(channel.join(), Some("done"));
+    /// ```
+    pub fn into_channel(mut self, capacity: usize) -> Prefetch<Item, Option<Fut::Output>> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let thread = thread::spawn(move || {
+            for item in self.by_ref() {
+                sender.send(item).ok()?;
+            }
+            self.into_return()
+        });
+        Prefetch {
+            receiver: Some(receiver),
+            thread: Some(thread),
+            output: None,
+        }
+    }
+}
+
+/// Turn code which writes to an [io::Write] into an iterator of the
+/// chunks it writes.
+///
+/// `f` runs on its own thread, at most `capacity` chunks ahead of the
+/// consumer. Each nonempty `write` becomes one `Ok` item. If `f`
+/// returns an error, it's the last item. Once the iterator is dropped,
+/// writes fail with [io::ErrorKind::BrokenPipe].
+///
+/// ```
+/// use gen::generate_from_write;
+/// use std::io::Write;
+///
+/// let chunks = generate_from_write(4, |out| {
+///     for i in 0..3 {
+///         write!(out, "row {i};")?;
+///     }
+///     Ok(())
+/// });
+///
+/// let bytes = chunks.collect::<Result<Vec<_>, _>>().unwrap().concat();
+/// assert_eq!(bytes, b"row 0;row 1;row 2;");
+/// ```
+pub fn generate_from_write<F>(capacity: usize, f: F) -> Prefetch<io::Result<Vec<u8>>>
+where
+    F: FnOnce(&mut ChunkWriter) -> io::Result<()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    let thread = thread::spawn(move || {
+        let mut writer = ChunkWriter { sender };
+        if let Err(err) = f(&mut writer) {
+            // Fails only if Prefetch is gone, which makes it moot
+            let _ = writer.sender.send(Err(err));
+        }
+    });
+    Prefetch {
+        receiver: Some(receiver),
+        thread: Some(thread),
+        output: None,
+    }
+}
+
+/// Sends each write to a [Prefetch] iterator as a chunk
+///
+/// [generate_from_write] passes this to its function.
+pub struct ChunkWriter {
+    sender: SyncSender<io::Result<Vec<u8>>>,
+}
+
+impl io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.sender
+            .send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An iterator over items produced on a background thread
+///
+/// [generate_prefetch], [generate_from_write], and
+/// [Generator::into_channel] return this. `Output` is what the thread
+/// returns. Dropping it stops the producer at its next `yield_` or
+/// write and waits for the thread to exit.
+pub struct Prefetch<Item, Output = ()> {
+    receiver: Option<Receiver<Item>>,
+    thread: Option<JoinHandle<Output>>,
+    // What the thread returned, once it has exited
+    output: Option<Output>,
+}
+
+impl<Item, Output> Prefetch<Item, Output> {
+    /// Stop the producer at its next item, if it's still running, and
+    /// return what the thread returned. Items not yet received are
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the thread's panic, if it panicked.
+    pub fn join(mut self) -> Output {
+        self.finish();
+        self.output
+            .take()
+            .expect("Prefetch thread's output taken twice")
+    }
+
+    /// Like [Iterator::next], but gives up after `duration` if the
+    /// thread hasn't produced an item.
+    pub fn next_timeout(&mut self, duration: Duration) -> Result<Option<Item>, TimedOut> {
+        let Some(receiver) = &self.receiver else {
+            return Ok(None);
+        };
+        match receiver.recv_timeout(duration) {
+            Ok(item) => Ok(Some(item)),
+            Err(RecvTimeoutError::Timeout) => Err(TimedOut),
+            Err(RecvTimeoutError::Disconnected) => Ok(self.finish()),
+        }
+    }
+
+    // Disconnect and wait for the thread, propagating its panic, if
+    // any
+    fn finish(&mut self) -> Option<Item> {
+        self.receiver = None;
+        match self.thread.take()?.join() {
+            Ok(output) => self.output = Some(output),
+            Err(payload) => panic::resume_unwind(payload),
+        }
+        None
+    }
+}
+
+impl<Item, Output> Iterator for Prefetch<Item, Output> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Ok(item) = self.receiver.as_ref()?.recv() {
+            return Some(item);
+        }
+        self.finish()
+    }
+}
+
+impl<Item, Output> Drop for Prefetch<Item, Output> {
+    fn drop(&mut self) {
+        // Disconnect first, so the thread's next send fails
+        self.receiver = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Reporting progress outside the item stream.
+
+use crate::{shared::SharedState, Communication, Generator};
+use core::future::Future;
+
+/// How far along the async function says it is
+///
+/// [Communication::progress] sets this.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Progress {
+    /// Units of work done, e.g. bytes or records
+    Count(u64),
+
+    /// Portion done, from `0.0` to `1.0`
+    Fraction(f64),
+}
+
+impl From<u64> for Progress {
+    fn from(count: u64) -> Self {
+        Progress::Count(count)
+    }
+}
+
+impl From<f64> for Progress {
+    fn from(fraction: f64) -> Self {
+        Progress::Fraction(fraction)
+    }
+}
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Report progress, which [Generator::progress] reads. It's
+    /// separate from the items, and each report replaces the last.
+    ///
+    /// ```
+    /// use gen::{generate, Progress};
+    ///
+    /// let files = ["a.txt", "b.txt", "c.txt", "d.txt"];
+    /// let mut lines = generate(|co| async move {
+    ///     for (i, file) in files.iter().enumerate() {
+    ///         co.yield_(format!("contents of {file}")).await;
+    ///         co.progress((i + 1) as f64 / files.len() as f64);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(lines.progress(), None);
+    /// lines.next();
+    /// lines.next();
+    /// assert_eq!(lines.progress(), Some(Progress::Fraction(0.25)));
+    /// ```
+    pub fn progress(&self, progress: impl Into<Progress>) {
+        let progress = progress.into();
+        self.0.with(|slot| slot.progress = Some(progress));
+    }
+}
+
+impl<Item, Fut: Future, Resume, S: SharedState<Item, Resume>> Generator<Item, Fut, Resume, S> {
+    /// The last progress the async function reported with
+    /// [Communication::progress]
+    pub fn progress(&self) -> Option<Progress> {
+        self.shared.with(|slot| slot.progress)
+    }
+}
@@ -0,0 +1,79 @@
+// Generators which can start over.
+
+use crate::{generate, Communication, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Like [generate], but keeps `f` so the generator can
+/// [restart](Restartable::restart).
+///
+/// ```
+/// use gen::generate_restartable;
+///
+/// let mut countdown = generate_restartable(|co| async move {
+///     for i in (1..=3).rev() {
+///         co.yield_(i).await;
+///     }
+/// });
+///
+/// assert_eq!(countdown.next(), Some(3));
+/// countdown.restart();
+/// assert_eq!(countdown.by_ref().collect::<Vec<_>>(), [3, 2, 1]);
+/// countdown.restart();
+/// assert_eq!(countdown.count(), 3);
+/// ```
+pub fn generate_restartable<Item, F, Fut>(mut f: F) -> Restartable<Item, F, Fut>
+where
+    F: FnMut(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    Restartable {
+        generator: generate(&mut f),
+        f,
+    }
+}
+
+/// A [Generator] which can rebuild its async function and start over
+///
+/// [generate_restartable] returns this. It isn't a
+/// [FusedIterator](core::iter::FusedIterator): after
+/// [restart](Restartable::restart), items follow `None`.
+pub struct Restartable<Item, F, Fut: Future> {
+    f: F,
+    generator: Generator<Item, Pin<Box<Fut>>>,
+}
+
+impl<Item, F, Fut> Restartable<Item, F, Fut>
+where
+    F: FnMut(Communication<Item>) -> Fut,
+    Fut: Future,
+{
+    /// Drop the current async function and start a new one from the
+    /// beginning. It gets a fresh [Generator]; settings such as the
+    /// drop policy don't carry over.
+    pub fn restart(&mut self) {
+        self.generator = generate(&mut self.f);
+    }
+
+    /// The current [Generator]
+    pub fn generator(&self) -> &Generator<Item, Pin<Box<Fut>>> {
+        &self.generator
+    }
+
+    /// The current [Generator]
+    pub fn generator_mut(&mut self) -> &mut Generator<Item, Pin<Box<Fut>>> {
+        &mut self.generator
+    }
+}
+
+impl<Item, F, Fut: Future> Iterator for Restartable<Item, F, Fut> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        self.generator.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.generator.size_hint()
+    }
+}
@@ -0,0 +1,127 @@
+//! Round-robin scheduling of many generators.
+//!
+//! A [Scheduler] holds generators and, each time [Scheduler::run_for]
+//! is called, polls them in turn within a budget. That suits game
+//! scripts and simulations which advance every generator a little per
+//! frame or tick.
+//!
+//! ```
+//! use gen::{generate, scheduler::Scheduler};
+//!
+//! let mut scheduler = Scheduler::new();
+//! let walker = scheduler.spawn(generate(|co| async move {
+//!     for step in 0..3 {
+//!         co.yield_(format!("walk {step}")).await;
+//!     }
+//! }));
+//! let talker = scheduler.spawn(generate(|co| async move {
+//!     co.yield_("hello".to_owned()).await;
+//! }));
+//!
+//! let items = scheduler.run_for(4);
+//! assert_eq!(
+//!     items,
+//!     [
+//!         (walker, "walk 0".to_owned()),
+//!         (talker, "hello".to_owned()),
+//!         (walker, "walk 1".to_owned()),
+//!     ]
+//! );
+//! assert_eq!(scheduler.len(), 1);
+//! ```
+
+use crate::{shared::SharedState, Budgeted, Generator};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::future::Future;
+
+/// Identifies a generator within its [Scheduler]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+// A generator with its types erased
+trait Task<Item> {
+    fn step(&mut self) -> Budgeted<Item>;
+}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> Task<Item>
+    for Generator<Item, Fut, Resume, S>
+{
+    fn step(&mut self) -> Budgeted<Item> {
+        self.next_with_budget(1)
+    }
+}
+
+/// Drives many generators round-robin
+///
+/// See the [module documentation](self).
+pub struct Scheduler<'a, Item> {
+    tasks: VecDeque<(TaskId, Box<dyn Task<Item> + 'a>)>,
+    next_id: u64,
+}
+
+impl<'a, Item> Scheduler<'a, Item> {
+    /// A scheduler with no generators
+    pub fn new() -> Self {
+        Self {
+            tasks: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Add a generator, which runs last in the current round.
+    pub fn spawn<Fut, Resume, S>(&mut self, generator: Generator<Item, Fut, Resume, S>) -> TaskId
+    where
+        Item: 'a,
+        Fut: Future + Unpin + 'a,
+        Resume: Default + 'a,
+        S: SharedState<Item, Resume> + 'a,
+    {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        self.tasks.push_back((id, Box::new(generator)));
+        id
+    }
+
+    /// Poll generators in turn, `budget` polls in all, returning the
+    /// items they yield in order. Each turn polls one generator once.
+    /// Finished generators are removed.
+    pub fn run_for(&mut self, budget: usize) -> Vec<(TaskId, Item)> {
+        let mut items = Vec::new();
+        for _ in 0..budget {
+            let Some((id, mut task)) = self.tasks.pop_front() else {
+                break;
+            };
+            match task.step() {
+                Budgeted::Item(item) => items.push((id, item)),
+                Budgeted::Exhausted => (),
+                Budgeted::Done => continue,
+            }
+            self.tasks.push_back((id, task));
+        }
+        items
+    }
+
+    /// Remove a generator, dropping it. Returns whether it was
+    /// present.
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        let before = self.tasks.len();
+        self.tasks.retain(|(task_id, _)| *task_id != id);
+        self.tasks.len() != before
+    }
+
+    /// How many generators haven't finished
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether every generator has finished
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+impl<Item> Default for Scheduler<'_, Item> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
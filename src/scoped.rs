@@ -0,0 +1,142 @@
+//! Generators which borrow caller-owned storage instead of allocating it.
+//!
+//! The crate root's generators keep their shared state in an `Arc`.
+//! These keep it in a [Storage] that the caller owns, so the only
+//! allocations left are the boxed future and, the first time the
+//! storage is used, its item queue. The state can't live inside
+//! the generator itself: the future would then borrow the generator
+//! that owns it, which safe Rust can't express for a value that moves.
+//!
+//! ```
+//! use gen::scoped::{generate, Storage};
+//!
+//! let mut storage = Storage::new();
+//! let iter = generate(&mut storage, |co| async move {
+//!     for i in 0..4 {
+//!         co.yield_(i).await;
+//!     }
+//! });
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2, 3]);
+//! ```
+//!
+//! [generate_on_stack] goes further and pins the future on the stack.
+//! The generator then only lives for the duration of a callback. The
+//! first `yield_` allocates the item queue inside the [Storage];
+//! after that, reusing the storage allocates nothing.
+//!
+//! ```
+//! use gen::scoped::{generate_on_stack, Storage};
+//!
+//! let mut storage = Storage::new();
+//! let sum: i32 = generate_on_stack(
+//!     &mut storage,
+//!     |co| async move {
+//!         for i in 0..4 {
+//!             co.yield_(i).await;
+//!         }
+//!     },
+//!     |iter| iter.sum(),
+//! );
+//!
+//! assert_eq!(sum, 6);
+//! ```
+
+use crate::shared::{self, Lock, Slot};
+use alloc::boxed::Box;
+use core::{
+    future::Future,
+    pin::{pin, Pin},
+};
+
+/// State shared between a scoped [Generator] and its [Communication]
+///
+/// A `Storage` may be reused once the generator that borrowed it is
+/// gone. It keeps its allocations across uses.
+pub struct Storage<Item, Resume = ()>(Lock<Slot<Item, Resume>>);
+
+impl<Item, Resume> Storage<Item, Resume> {
+    /// Create empty storage.
+    pub fn new() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<Item, Resume> Default for Storage<Item, Resume> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) type ScopedShared<'a, Item, Resume> = &'a Lock<Slot<Item, Resume>>;
+
+/// A [crate::Generator] which borrows its [Storage]
+pub type Generator<'a, Item, Fut, Resume = ()> =
+    crate::Generator<Item, Fut, Resume, ScopedShared<'a, Item, Resume>>;
+
+/// A [crate::Communication] which borrows its [Storage]
+pub type Communication<'a, Item, Resume = ()> =
+    crate::Communication<Item, Resume, ScopedShared<'a, Item, Resume>>;
+
+/// A scoped [Generator] whose future is pinned on the stack
+pub type StackGenerator<'a, 'f, Item, Fut, Resume = ()> =
+    Generator<'a, Item, Pin<&'f mut Fut>, Resume>;
+
+/// Version of [crate::generate] which borrows `storage`
+pub fn generate<'a, Item, F, Fut>(
+    storage: &'a mut Storage<Item>,
+    f: F,
+) -> Generator<'a, Item, Pin<Box<Fut>>>
+where
+    F: FnOnce(Communication<'a, Item>) -> Fut,
+    Fut: Future,
+{
+    generate_bidi(storage, f)
+}
+
+/// Version of [crate::generate_bidi] which borrows `storage`
+pub fn generate_bidi<'a, Item, Resume, F, Fut>(
+    storage: &'a mut Storage<Item, Resume>,
+    f: F,
+) -> Generator<'a, Item, Pin<Box<Fut>>, Resume>
+where
+    F: FnOnce(Communication<'a, Item, Resume>) -> Fut,
+    Fut: Future,
+{
+    // A previous generator may have left an item or resume value behind.
+    shared::get_mut(&mut storage.0).reset();
+    Generator::new_boxed(&storage.0, f)
+}
+
+/// Version of [generate] which pins the future on the stack instead of
+/// boxing it. The generator is passed to `scope`, and this returns
+/// what `scope` returns.
+pub fn generate_on_stack<'a, Item, F, Fut, T>(
+    storage: &'a mut Storage<Item>,
+    f: F,
+    scope: impl FnOnce(StackGenerator<'a, '_, Item, Fut>) -> T,
+) -> T
+where
+    F: FnOnce(Communication<'a, Item>) -> Fut,
+    Fut: Future,
+{
+    generate_bidi_on_stack(storage, f, scope)
+}
+
+/// Version of [generate_bidi] which pins the future on the stack
+/// instead of boxing it. The generator is passed to `scope`, and this
+/// returns what `scope` returns.
+pub fn generate_bidi_on_stack<'a, Item, Resume, F, Fut, T>(
+    storage: &'a mut Storage<Item, Resume>,
+    f: F,
+    scope: impl FnOnce(StackGenerator<'a, '_, Item, Fut, Resume>) -> T,
+) -> T
+where
+    F: FnOnce(Communication<'a, Item, Resume>) -> Fut,
+    Fut: Future,
+{
+    shared::get_mut(&mut storage.0).reset();
+    let shared = &storage.0;
+    let future = pin!(f(crate::Communication::new(shared)));
+    scope(Generator::new(shared, Some(future)))
+}
@@ -0,0 +1,272 @@
+// Storage shared between Communication and Generator.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{cell::RefCell, ops::DerefMut};
+
+/// Items on their way to Generator, a resume value on its way back to
+/// the async function, and whether Generator::close was called.
+///
+/// Each yield_ gets a ticket: the index of its item among all items
+/// ever queued. Items leave the queue in ticket order.
+pub struct Slot<Item, Resume> {
+    pub(crate) items: VecDeque<Item>,
+    // Number of items Generator has taken from the queue
+    pub(crate) delivered: u64,
+    // The resume value and the ticket of the yield_ it's for
+    pub(crate) resume: Option<(u64, Resume)>,
+    // Tickets below this were delivered in bulk; see deliver_all.
+    // Their yield_s resolve to the fn's value, unless `resume` is
+    // theirs.
+    pub(crate) resolved_through: Option<(u64, fn() -> Resume)>,
+    pub(crate) closed: bool,
+    // How many items yield_ may queue before suspending, and what it
+    // resolves to then; see Generator::set_buffer.
+    pub(crate) buffer: Option<(usize, fn() -> Resume)>,
+    // Items with tickets below this came from one yield_chunk, so
+    // Generator takes them without polling; see
+    // Communication::yield_chunk.
+    pub(crate) chunk_end: u64,
+    // Bounds on how many more times yield_ will queue an item
+    pub(crate) remaining: (usize, Option<usize>),
+    pub(crate) token: Option<crate::CancellationToken>,
+    pub(crate) progress: Option<crate::Progress>,
+    pub(crate) metadata: BTreeMap<String, String>,
+    // Run if the future is dropped before it completes
+    pub(crate) on_cancel: Vec<Box<dyn FnOnce() + Send>>,
+    // Receives items dropped without being delivered
+    pub(crate) on_unconsumed: Option<Box<dyn FnMut(Item) + Send>>,
+    // Whether a Generator polls the future. If so, YieldFuture reports
+    // misuse here instead of panicking.
+    pub(crate) driven: bool,
+    pub(crate) error: Option<crate::Error>,
+}
+
+impl<Item, Resume> Slot<Item, Resume> {
+    // Queue an item, returning its ticket
+    pub(crate) fn push(&mut self, item: Item) -> u64 {
+        let (lower, upper) = &mut self.remaining;
+        *lower = lower.saturating_sub(1);
+        *upper = upper.map(|upper| upper.saturating_sub(1));
+        self.items.push_back(item);
+        self.delivered + self.items.len() as u64 - 1
+    }
+
+    // Bounds on how many items Generator has yet to take
+    pub(crate) fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.remaining;
+        let queued = self.items.len();
+        (
+            lower.saturating_add(queued),
+            upper.and_then(|upper| upper.checked_add(queued)),
+        )
+    }
+
+    // Take the next item for Generator
+    pub(crate) fn pop(&mut self) -> Option<Item> {
+        let item = self.items.pop_front()?;
+        self.delivered += 1;
+        Some(item)
+    }
+
+    // Take the queued items. An empty queue keeps its allocation.
+    pub(crate) fn take_items(&mut self) -> VecDeque<Item> {
+        match self.items.is_empty() {
+            true => VecDeque::new(),
+            false => core::mem::take(&mut self.items),
+        }
+    }
+
+    // Empty the slot for another generator, keeping what it has
+    // allocated
+    pub(crate) fn reset(&mut self) {
+        let mut items = core::mem::take(&mut self.items);
+        items.clear();
+        let mut on_cancel = core::mem::take(&mut self.on_cancel);
+        on_cancel.clear();
+        *self = Self {
+            items,
+            on_cancel,
+            ..Self::default()
+        };
+    }
+
+    // Take every queued item at once. There's only one resume value
+    // per poll, so the yield_s waiting on them, and on the item taken
+    // before them, resolve to `resume()`.
+    pub(crate) fn deliver_all(&mut self, resume: fn() -> Resume) -> VecDeque<Item> {
+        let items = self.take_items();
+        if !items.is_empty() {
+            self.delivered += items.len() as u64;
+            self.resolved_through = Some((self.delivered, resume));
+        }
+        items
+    }
+}
+
+impl<Item, Resume> Default for Slot<Item, Resume> {
+    fn default() -> Self {
+        Self {
+            items: VecDeque::new(),
+            delivered: 0,
+            resume: None,
+            resolved_through: None,
+            closed: false,
+            buffer: None,
+            chunk_end: 0,
+            remaining: (0, None),
+            token: None,
+            progress: None,
+            metadata: BTreeMap::new(),
+            on_cancel: Vec::new(),
+            on_unconsumed: None,
+            driven: false,
+            error: None,
+        }
+    }
+}
+
+/// A handle to a [Slot]. Generator and Communication each hold one.
+///
+/// This is sealed: its implementations are the crate's storage
+/// backends.
+pub trait SharedState<Item, Resume>: Clone {
+    /// Run `f` with exclusive access to the slot.
+    fn with<T>(&self, f: impl FnOnce(&mut Slot<Item, Resume>) -> T) -> T;
+}
+
+// Lock guarding thread-safe storage. There's no Mutex without std;
+// a spinlock stands in with the spin feature, or else RefCell, which
+// makes those generators !Send.
+#[cfg(feature = "std")]
+pub type Lock<T> = std::sync::Mutex<T>;
+#[cfg(all(feature = "spin", not(feature = "std")))]
+pub type Lock<T> = SpinLock<T>;
+#[cfg(not(any(feature = "std", feature = "spin")))]
+pub type Lock<T> = RefCell<T>;
+
+// Busy-waits for the lock. Slot accesses are short and never nest, so
+// waiting is brief, and unwinding releases the lock.
+#[cfg(all(feature = "spin", not(feature = "std")))]
+#[derive(Default)]
+pub struct SpinLock<T> {
+    locked: core::sync::atomic::AtomicBool,
+    value: core::cell::UnsafeCell<T>,
+}
+
+// SAFETY: `lock` hands out at most one SpinGuard at a time, so only
+// one thread reaches the value at once; T: Send lets it be that
+// thread. This is Mutex's bound.
+#[cfg(all(feature = "spin", not(feature = "std")))]
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+impl<T> SpinLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            locked: Default::default(),
+            value: core::cell::UnsafeCell::new(value),
+        }
+    }
+}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+pub struct SpinGuard<'a, T>(&'a SpinLock<T>);
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+impl<T> core::ops::Deref for SpinGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: a SpinGuard exists only while `locked` is set by its
+        // `lock` call, so no other reference to the value exists.
+        unsafe { &*self.0.value.get() }
+    }
+}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+impl<T> DerefMut for SpinGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as in deref; `&mut self` also rules out a shared
+        // borrow through this guard.
+        unsafe { &mut *self.0.value.get() }
+    }
+}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+impl<T> Drop for SpinGuard<'_, T> {
+    fn drop(&mut self) {
+        self.0
+            .locked
+            .store(false, core::sync::atomic::Ordering::Release);
+    }
+}
+
+// A panic caught by Generator may poison the lock; the slot is still
+// consistent, since Generator clears it afterwards.
+#[cfg(feature = "std")]
+pub fn lock<T>(lock: &Lock<T>) -> impl DerefMut<Target = T> + '_ {
+    lock.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+pub fn lock<T>(lock: &Lock<T>) -> impl DerefMut<Target = T> + '_ {
+    use core::sync::atomic::Ordering;
+    while lock
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    SpinGuard(lock)
+}
+
+#[cfg(not(any(feature = "std", feature = "spin")))]
+pub fn lock<T>(lock: &Lock<T>) -> impl DerefMut<Target = T> + '_ {
+    lock.borrow_mut()
+}
+
+#[cfg(feature = "std")]
+pub fn get_mut<T>(lock: &mut Lock<T>) -> &mut T {
+    lock.get_mut()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+#[cfg(all(feature = "spin", not(feature = "std")))]
+pub fn get_mut<T>(lock: &mut Lock<T>) -> &mut T {
+    lock.value.get_mut()
+}
+
+#[cfg(not(any(feature = "std", feature = "spin")))]
+pub fn get_mut<T>(lock: &mut Lock<T>) -> &mut T {
+    lock.get_mut()
+}
+
+// Thread-safe storage. This lets Generator move between threads.
+impl<Item, Resume> SharedState<Item, Resume> for Arc<Lock<Slot<Item, Resume>>> {
+    fn with<T>(&self, f: impl FnOnce(&mut Slot<Item, Resume>) -> T) -> T {
+        f(&mut lock(self))
+    }
+}
+
+// Single-threaded storage with no locking or atomic refcounting.
+impl<Item, Resume> SharedState<Item, Resume> for Rc<RefCell<Slot<Item, Resume>>> {
+    fn with<T>(&self, f: impl FnOnce(&mut Slot<Item, Resume>) -> T) -> T {
+        f(&mut self.borrow_mut())
+    }
+}
+
+// Thread-safe storage owned by the caller instead of allocated.
+impl<Item, Resume> SharedState<Item, Resume> for &Lock<Slot<Item, Resume>> {
+    fn with<T>(&self, f: impl FnOnce(&mut Slot<Item, Resume>) -> T) -> T {
+        f(&mut lock(self))
+    }
+}
@@ -0,0 +1,101 @@
+// Generators built from async step functions instead of a body which
+// calls yield_.
+
+use crate::{generate, Generator};
+use alloc::boxed::Box;
+use core::{future::Future, pin::Pin};
+
+/// Build a generator from a state and an async step function, like
+/// `futures::stream::unfold`.
+///
+/// Each step consumes the state and returns the next item and state,
+/// or `None` to finish.
+///
+/// ```
+/// let fibonacci = gen::unfold((0, 1), |(a, b)| async move { Some((a, (b, a + b))) });
+/// assert_eq!(fibonacci.take(6).collect::<Vec<_>>(), [0, 1, 1, 2, 3, 5]);
+/// ```
+pub fn unfold<Item, State, F, Fut>(
+    initial_state: State,
+    mut f: F,
+) -> Generator<Item, Pin<Box<impl Future<Output = ()>>>>
+where
+    F: FnMut(State) -> Fut,
+    Fut: Future<Output = Option<(Item, State)>>,
+{
+    generate(move |co| async move {
+        let mut state = initial_state;
+        while let Some((item, next)) = f(state).await {
+            co.yield_(item).await;
+            state = next;
+        }
+    })
+}
+
+/// Build a generator which calls `f` for each item until it returns
+/// `None`, like [core::iter::from_fn].
+///
+/// ```
+/// let mut requests = vec!["GET /", "GET /about"];
+/// let handled = gen::from_fn_async(|| {
+///     let request = requests.pop();
+///     async move { Some(format!("handled {}", request?)) }
+/// });
+/// assert_eq!(handled.collect::<Vec<_>>(), ["handled GET /about", "handled GET /"]);
+/// ```
+pub fn from_fn_async<Item, F, Fut>(mut f: F) -> Generator<Item, Pin<Box<impl Future<Output = ()>>>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<Item>>,
+{
+    generate(move |co| async move {
+        while let Some(item) = f().await {
+            co.yield_(item).await;
+        }
+    })
+}
+
+/// Build a generator whose items each come from the one before, like
+/// [core::iter::successors].
+///
+/// `first` is the first item, if any. `succ` computes the next from
+/// the previous, or `None` to finish.
+///
+/// ```
+/// // Each page of results names the next
+/// let pages = gen::successors_async(Some(1), |&page| async move {
+///     (page < 3).then_some(page + 1)
+/// });
+/// assert_eq!(pages.collect::<Vec<_>>(), [1, 2, 3]);
+/// ```
+pub fn successors_async<Item, F, Fut>(
+    first: Option<Item>,
+    mut succ: F,
+) -> Generator<Item, Pin<Box<impl Future<Output = ()>>>>
+where
+    F: FnMut(&Item) -> Fut,
+    Fut: Future<Output = Option<Item>>,
+{
+    generate(move |co| async move {
+        let mut next = first;
+        while let Some(item) = next {
+            next = succ(&item).await;
+            co.yield_(item).await;
+        }
+    })
+}
+
+/// A generator which yields `future`'s output, then finishes.
+///
+/// A future which is pending without waking itself needs a
+/// [Driver](crate::Driver); see [Generator::set_driver].
+///
+/// ```
+/// let answer = gen::once_future(async { 6 * 7 });
+/// assert_eq!(answer.collect::<Vec<_>>(), [42]);
+/// ```
+pub fn once_future<Fut: Future>(
+    future: Fut,
+) -> Generator<Fut::Output, Pin<Box<impl Future<Output = ()>>>> {
+    generate(move |co| async move { co.yield_(future.await).await })
+}
@@ -0,0 +1,104 @@
+// Splitting one generator between two consumers.
+
+use crate::{
+    shared::{self, Lock, SharedState},
+    Generator, SyncShared,
+};
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{future::Future, iter::FusedIterator};
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Split into two iterators which each see every item.
+    ///
+    /// The async function runs once. Items the slower side hasn't
+    /// reached yet wait in a buffer, so letting one side run far
+    /// ahead holds onto that many clones. Once one side is dropped,
+    /// the other stops buffering.
+    ///
+    /// ```
+    /// use gen::generate;
+    ///
+    /// let (evens, all) = generate(|co| async move {
+    ///     for i in 0..5 {
+    ///         co.yield_(i).await;
+    ///     }
+    /// })
+    /// .tee();
+    ///
+    /// assert_eq!(evens.step_by(2).collect::<Vec<_>>(), [0, 2, 4]);
+    /// assert_eq!(all.collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn tee(self) -> (Tee<Item, Fut, Resume, S>, Tee<Item, Fut, Resume, S>) {
+        let state = Arc::new(Lock::new(TeeState {
+            generator: self,
+            buffer: VecDeque::new(),
+            behind: 0,
+        }));
+        (
+            Tee {
+                state: state.clone(),
+                side: 0,
+            },
+            Tee { state, side: 1 },
+        )
+    }
+}
+
+/// One of two iterators sharing a [Generator]
+///
+/// [Generator::tee] returns these.
+pub struct Tee<Item, Fut: Future, Resume = (), S = SyncShared<Item, Resume>> {
+    state: Arc<Lock<TeeState<Item, Fut, Resume, S>>>,
+    side: usize,
+}
+
+struct TeeState<Item, Fut: Future, Resume, S> {
+    generator: Generator<Item, Fut, Resume, S>,
+    // Items side `behind` has yet to see. The other side already has.
+    buffer: VecDeque<Item>,
+    behind: usize,
+}
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> Iterator
+    for Tee<Item, Fut, Resume, S>
+{
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        let alone = Arc::strong_count(&self.state) == 1;
+        let mut state = shared::lock(&self.state);
+        if state.behind == self.side {
+            if let Some(item) = state.buffer.pop_front() {
+                return Some(item);
+            }
+        }
+        let item = state.generator.next()?;
+        if !alone {
+            state.buffer.push_back(item.clone());
+            state.behind = 1 - self.side;
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let state = shared::lock(&self.state);
+        let (lower, upper) = state.generator.size_hint();
+        let buffered = if state.behind == self.side {
+            state.buffer.len()
+        } else {
+            0
+        };
+        (
+            lower.saturating_add(buffered),
+            upper.and_then(|upper| upper.checked_add(buffered)),
+        )
+    }
+}
+
+impl<Item: Clone, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>> FusedIterator
+    for Tee<Item, Fut, Resume, S>
+{
+}
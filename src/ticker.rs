@@ -0,0 +1,123 @@
+// Resumable coroutines which yield nothing.
+
+use alloc::boxed::Box;
+use core::{
+    future::Future,
+    iter::FusedIterator,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Turn an async function into a coroutine which suspends at each
+/// [Tick::tick] and yields no items.
+///
+/// This skips [crate::generate]'s shared state: each [Iterator::next]
+/// polls the future once, and anything which leaves it pending counts
+/// as a tick.
+///
+/// ```
+/// use gen::tick;
+///
+/// let mut frames = 0;
+/// let mut animation = tick(|t| {
+///     let frames = &mut frames;
+///     async move {
+///         for _ in 0..3 {
+///             *frames += 1;
+///             t.tick().await;
+///         }
+///         "finished"
+///     }
+/// });
+///
+/// assert_eq!(animation.by_ref().count(), 3);
+/// assert_eq!(animation.return_value(), Some(&"finished"));
+/// drop(animation);
+/// assert_eq!(frames, 3);
+/// ```
+pub fn tick<F, Fut>(f: F) -> Ticker<Pin<Box<Fut>>>
+where
+    F: FnOnce(Tick) -> Fut,
+    Fut: Future,
+{
+    Ticker {
+        future: Some(Box::pin(f(Tick(())))),
+        return_value: None,
+    }
+}
+
+/// Suspends a [Ticker]'s async function
+///
+/// The function passed to [tick] receives this.
+#[derive(Clone, Copy, Debug)]
+pub struct Tick(());
+
+impl Tick {
+    /// Suspend until the next [Iterator::next].
+    pub fn tick(&self) -> TickFuture {
+        TickFuture(false)
+    }
+}
+
+/// Future for [Tick::tick]
+#[derive(Debug)]
+pub struct TickFuture(bool);
+
+impl Future for TickFuture {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        Poll::Pending
+    }
+}
+
+/// A coroutine driven one tick at a time
+///
+/// [tick] returns this. Its [Iterator] yields `()` per tick.
+pub struct Ticker<Fut: Future> {
+    future: Option<Fut>,
+    return_value: Option<Fut::Output>,
+}
+
+impl<Fut: Future> Ticker<Fut> {
+    /// Whether the async function has returned or panicked
+    pub fn is_finished(&self) -> bool {
+        self.future.is_none()
+    }
+
+    /// The async function's return value, once it has returned
+    pub fn return_value(&self) -> Option<&Fut::Output> {
+        self.return_value.as_ref()
+    }
+
+    /// Consume this, returning the async function's return value, if
+    /// it has returned.
+    pub fn into_return(self) -> Option<Fut::Output> {
+        self.return_value
+    }
+}
+
+impl<Fut: Future + Unpin> Iterator for Ticker<Fut> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<()> {
+        // Taken while polling, so a panic leaves the ticker finished
+        let mut future = self.future.take()?;
+        match Pin::new(&mut future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Pending => {
+                self.future = Some(future);
+                Some(())
+            }
+            Poll::Ready(value) => {
+                self.return_value = Some(value);
+                None
+            }
+        }
+    }
+}
+
+impl<Fut: Future + Unpin> FusedIterator for Ticker<Fut> {}
@@ -0,0 +1,252 @@
+// Timers for the synchronous executor.
+
+use crate::{shared::SharedState, Communication, Generator};
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+impl<Item, Resume, S: SharedState<Item, Resume>> Communication<Item, Resume, S> {
+    /// Pause the async function for `duration`.
+    ///
+    /// This blocks the thread that's resuming the generator, so it
+    /// needs no runtime. Don't use it where the generator is polled
+    /// asynchronously.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        Sleep {
+            deadline: Instant::now() + duration,
+        }
+    }
+
+    /// Await `future`, giving up after `duration`.
+    ///
+    /// While `future` is pending, this blocks the thread that's resuming
+    /// the generator until `future` wakes or time runs out, so it needs
+    /// no runtime. Don't use it where the generator is polled
+    /// asynchronously.
+    ///
+    /// ```
+    /// use gen::{generate, TimedOut};
+    /// use std::time::Duration;
+    ///
+    /// let mut iter = generate(|co| async move {
+    ///     let never = std::future::pending::<i32>();
+    ///     let result = co.timeout(Duration::from_millis(10), never).await;
+    ///     co.yield_(result).await;
+    /// });
+    ///
+    /// assert_eq!(iter.next(), Some(Err(TimedOut)));
+    /// ```
+    pub async fn timeout<F: Future>(
+        &self,
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, TimedOut> {
+        let deadline = Instant::now() + duration;
+        let mut future = pin!(future);
+        poll_fn(|_| loop {
+            let unparker = Arc::new(Unparker {
+                thread: thread::current(),
+                woken: AtomicBool::new(false),
+            });
+            let waker = Waker::from(unparker.clone());
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return Poll::Ready(Ok(output));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Poll::Ready(Err(TimedOut));
+            }
+            if !unparker.woken.load(Ordering::Acquire) {
+                // May wake spuriously; the loop polls again either way.
+                thread::park_timeout(deadline - now);
+            }
+        })
+        .await
+    }
+}
+
+// Unparks the thread which polled the future
+struct Unparker {
+    thread: Thread,
+    woken: AtomicBool,
+}
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+/// Future returned by [Communication::sleep]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let now = Instant::now();
+        if now < self.deadline {
+            thread::sleep(self.deadline - now);
+        }
+        Poll::Ready(())
+    }
+}
+
+/// A wait ran out of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+impl<Item, Fut: Future + Unpin, Resume: Default, S: SharedState<Item, Resume>>
+    Generator<Item, Fut, Resume, S>
+{
+    /// Like [Iterator::next], but gives up after `duration`.
+    ///
+    /// While the async function awaits futures besides `yield_`, this
+    /// blocks until they wake it or time runs out. After a timeout the
+    /// generator is intact; call again to keep waiting. This doesn't
+    /// use the generator's [crate::Driver].
+    ///
+    /// ```
+    /// use gen::{generate, TimedOut};
+    /// use std::{sync::mpsc, thread, time::Duration};
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// let mut events = generate(|co| async move {
+    ///     // Stands in for a future which wakes on an external event
+    ///     let event = std::future::poll_fn(|cx| match rx.try_recv() {
+    ///         Ok(event) => std::task::Poll::Ready(event),
+    ///         Err(_) => {
+    ///             let waker = cx.waker().clone();
+    ///             thread::spawn(move || {
+    ///                 thread::sleep(Duration::from_millis(1));
+    ///                 waker.wake();
+    ///             });
+    ///             std::task::Poll::Pending
+    ///         }
+    ///     })
+    ///     .await;
+    ///     co.yield_(event).await;
+    /// });
+    ///
+    /// assert_eq!(events.next_timeout(Duration::from_millis(20)), Err(TimedOut));
+    /// tx.send("click").unwrap();
+    /// assert_eq!(events.next_timeout(Duration::from_secs(5)), Ok(Some("click")));
+    /// ```
+    pub fn next_timeout(&mut self, duration: Duration) -> Result<Option<Item>, TimedOut> {
+        let deadline = Instant::now() + duration;
+        let unparker = Arc::new(Unparker {
+            thread: thread::current(),
+            woken: AtomicBool::new(false),
+        });
+        let waker = Waker::from(unparker.clone());
+        loop {
+            unparker.woken.store(false, Ordering::Release);
+            if let Poll::Ready(item) = self.poll_next(&mut Context::from_waker(&waker)) {
+                return Ok(item);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(TimedOut);
+            }
+            if !unparker.woken.load(Ordering::Acquire) {
+                thread::park_timeout(deadline - now);
+            }
+        }
+    }
+}
+
+impl<Item, Fut: Future + Unpin, Resume, S> Generator<Item, Fut, Resume, S> {
+    /// Deliver items at most once per `min_interval`, sleeping in
+    /// [Iterator::next] as needed.
+    ///
+    /// ```
+    /// use gen::generate;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let events = generate(|co| async move {
+    ///     co.yield_all(0..3).await;
+    /// })
+    /// .throttle(Duration::from_millis(5));
+    ///
+    /// let start = Instant::now();
+    /// assert_eq!(events.collect::<Vec<_>>(), [0, 1, 2]);
+    /// assert!(start.elapsed() >= Duration::from_millis(10));
+    /// ```
+    pub fn throttle(self, min_interval: Duration) -> Throttle<Self> {
+        Throttle {
+            generator: self,
+            min_interval,
+            last: None,
+        }
+    }
+}
+
+/// A generator whose items are delivered at a limited rate
+///
+/// [Generator::throttle] returns this.
+pub struct Throttle<G> {
+    generator: G,
+    min_interval: Duration,
+    last: Option<Instant>,
+}
+
+impl<G> Throttle<G> {
+    /// The underlying generator
+    pub fn generator(&self) -> &G {
+        &self.generator
+    }
+
+    /// The underlying generator, e.g. to [Generator::close] it
+    pub fn generator_mut(&mut self) -> &mut G {
+        &mut self.generator
+    }
+
+    /// Consume this, returning the underlying generator.
+    pub fn into_generator(self) -> G {
+        self.generator
+    }
+}
+
+impl<G: Iterator> Iterator for Throttle<G> {
+    type Item = G::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.generator.next()?;
+        if let Some(last) = self.last {
+            let elapsed = last.elapsed();
+            if elapsed < self.min_interval {
+                thread::sleep(self.min_interval - elapsed);
+            }
+        }
+        self.last = Some(Instant::now());
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.generator.size_hint()
+    }
+}
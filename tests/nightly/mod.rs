@@ -0,0 +1,128 @@
+#[test]
+fn async_iterator() {
+    use gen::generate;
+    use std::{
+        async_iter::AsyncIterator,
+        future::Future,
+        pin::{pin, Pin},
+        task::{Context, Poll, Waker},
+    };
+
+    // Pending once, then ready
+    struct PendingOnce(bool);
+    impl Future for PendingOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    let mut iter = pin!(generate(|co| async move {
+        co.yield_(1).await;
+        PendingOnce(false).await;
+        co.yield_(2).await;
+    }));
+    let mut cx = Context::from_waker(Waker::noop());
+
+    assert_eq!(iter.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(iter.as_mut().poll_next(&mut cx), Poll::Pending);
+    assert_eq!(iter.as_mut().poll_next(&mut cx), Poll::Ready(Some(2)));
+    assert_eq!(iter.as_mut().poll_next(&mut cx), Poll::Ready(None));
+    assert_eq!(iter.as_mut().poll_next(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+fn std_coroutine() {
+    use gen::generate_bidi;
+    use std::{
+        ops::{Coroutine, CoroutineState},
+        pin::pin,
+    };
+
+    let mut generator = pin!(generate_bidi(|co| async move {
+        let a = co.yield_(1).await;
+        let b = co.yield_(a + 1).await;
+        a + b
+    }));
+
+    assert_eq!(generator.as_mut().resume(0), CoroutineState::Yielded(1));
+    assert_eq!(generator.as_mut().resume(10), CoroutineState::Yielded(11));
+    assert_eq!(generator.as_mut().resume(20), CoroutineState::Complete(30));
+}
+
+#[test]
+fn from_std_coroutine() {
+    use gen::from_coroutine;
+
+    let coroutine = #[coroutine]
+    |mut resume: u32| {
+        for i in 0..3 {
+            resume = yield i + resume;
+        }
+        "done"
+    };
+
+    let mut generator = from_coroutine(coroutine);
+    assert_eq!(generator.send(0), Some(0));
+    assert_eq!(generator.send(10), Some(11));
+    assert_eq!(generator.next(), Some(2));
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.into_return(), Some("done"));
+}
+
+#[test]
+fn forward_async_iterator() {
+    use gen::generate;
+
+    let inner = generate(|co| async move {
+        for i in 0..3 {
+            co.yield_(i).await;
+        }
+    });
+    let iter = generate(|co| async move {
+        co.yield_(-1).await;
+        co.forward(inner).await;
+        co.yield_(99).await;
+    });
+
+    assert_eq!(iter.collect::<Vec<_>>(), [-1, 0, 1, 2, 99]);
+}
+
+#[test]
+fn generate_in_allocator() {
+    use gen::generate_in;
+    use std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        ptr::NonNull,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+    // Counts live allocations, delegating to the global allocator
+    struct Counting;
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            LIVE.fetch_add(1, Ordering::SeqCst);
+            Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            LIVE.fetch_sub(1, Ordering::SeqCst);
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    let mut generator = generate_in(Counting, |co| async move {
+        co.yield_all([1, 2]).await;
+    });
+    assert_eq!(LIVE.load(Ordering::SeqCst), 1);
+    assert_eq!(generator.next(), Some(1));
+    drop(generator);
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
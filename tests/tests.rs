@@ -1,3 +1,55 @@
+#![cfg_attr(
+    feature = "nightly",
+    feature(
+        allocator_api,
+        async_iterator,
+        coroutines,
+        coroutine_trait,
+        stmt_expr_attributes
+    )
+)]
+
+// Kept out of this file so stable compilers don't parse nightly syntax
+#[cfg(feature = "nightly")]
+mod nightly;
+
+// Counts the current thread's allocations, for tests of what doesn't
+// allocate
+#[cfg(feature = "std")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static COUNTING: Counting = Counting;
+
+    // How many allocations `f` made
+    pub fn allocations(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+}
+
 #[test]
 fn no_state() {
     use gen::generate;
@@ -97,6 +149,7 @@ fn mut_ref() {
     assert_eq!(i, 45);
 }
 
+#[cfg(any(feature = "std", feature = "spin"))]
 #[test]
 fn move_iter_to_thread() {
     use gen::generate;
@@ -147,3 +200,2415 @@ fn move_iter_to_thread() {
     // Verify the async block modified `i`
     assert_eq!(i, 19);
 }
+
+#[test]
+fn return_value() {
+    use gen::generate;
+
+    let mut iter = generate(|co| async move {
+        let mut count = 0;
+        for i in 0..3 {
+            co.yield_(i).await;
+            count += 1;
+        }
+        format!("yielded {count}")
+    });
+
+    assert_eq!(iter.return_value(), None);
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(iter.return_value().map(String::as_str), Some("yielded 3"));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.into_return(), Some("yielded 3".to_owned()));
+}
+
+#[test]
+fn resume() {
+    use gen::{generate, GeneratorState};
+
+    let mut generator = generate(|co| async move {
+        co.yield_(Some(1)).await;
+        co.yield_(None).await;
+        "done"
+    });
+
+    assert_eq!(generator.resume(), GeneratorState::Yielded(Some(1)));
+    assert_eq!(generator.resume(), GeneratorState::Yielded(None));
+    assert_eq!(generator.resume(), GeneratorState::Complete("done"));
+    assert_eq!(generator.next(), None);
+}
+
+#[test]
+fn resume_after_next_finished() {
+    use gen::{generate, GeneratorState};
+
+    let mut generator = generate(|co| async move {
+        co.yield_(1).await;
+        2
+    });
+
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.resume(), GeneratorState::Complete(2));
+    assert_eq!(generator.return_value(), None);
+}
+
+#[test]
+fn bidi_send() {
+    use gen::generate_bidi;
+
+    let mut echo = generate_bidi(|co| async move {
+        let mut received = Vec::new();
+        let mut prompt = 0;
+        loop {
+            let line: String = co.yield_(prompt).await;
+            if line.is_empty() {
+                break received;
+            }
+            received.push(line);
+            prompt += 1;
+        }
+    });
+
+    // The first value starts the body; nothing is waiting for it.
+    assert_eq!(echo.send("ignored".to_owned()), Some(0));
+    assert_eq!(echo.send("a".to_owned()), Some(1));
+    assert_eq!(echo.send("b".to_owned()), Some(2));
+    assert_eq!(echo.next(), None);
+    assert_eq!(
+        echo.into_return(),
+        Some(vec!["a".to_owned(), "b".to_owned()])
+    );
+}
+
+#[test]
+fn throw_into_body() {
+    use gen::generate_bidi;
+
+    let mut generator = generate_bidi(|co| async move {
+        let mut errors = Vec::new();
+        for i in 0..3 {
+            if let Err(e) = co.yield_(i).await {
+                errors.push(e);
+            }
+        }
+        errors
+    });
+
+    assert_eq!(generator.send(Ok(())), Some(0));
+    assert_eq!(generator.throw("first"), Some(1));
+    assert_eq!(generator.send(Ok(())), Some(2));
+    assert_eq!(generator.throw("second"), None);
+    assert_eq!(generator.into_return(), Some(vec!["first", "second"]));
+}
+
+#[test]
+fn close_runs_cleanup() {
+    use gen::generate;
+    use std::cell::Cell;
+
+    let cleaned_up = &Cell::new(false);
+    let mut generator = generate(|co| async move {
+        let mut i = 0;
+        loop {
+            co.yield_(i).await;
+            if co.is_closed() {
+                cleaned_up.set(true);
+                return i;
+            }
+            i += 1;
+        }
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    assert_eq!(generator.next(), Some(1));
+    generator.close();
+    assert!(cleaned_up.get());
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.into_return(), Some(1));
+}
+
+#[test]
+fn close_drops_body_that_keeps_yielding() {
+    use gen::generate;
+    use std::cell::Cell;
+
+    struct Guard<'a>(&'a Cell<bool>);
+    impl Drop for Guard<'_> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let dropped = &Cell::new(false);
+    let mut generator = generate(|co| async move {
+        let _guard = Guard(dropped);
+        for i in 0.. {
+            co.yield_(i).await;
+        }
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    generator.close();
+    assert!(dropped.get());
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.return_value(), None);
+}
+
+#[test]
+fn close_on_drop() {
+    use gen::{generate, DropPolicy};
+    use std::cell::Cell;
+
+    let cleaned_up = &Cell::new(false);
+    let mut generator = generate(|co| async move {
+        co.yield_(0).await;
+        cleaned_up.set(co.is_closed());
+    });
+    generator.set_drop_policy(DropPolicy::Close);
+
+    assert_eq!(generator.next(), Some(0));
+    drop(generator);
+    assert!(cleaned_up.get());
+}
+
+#[test]
+fn local_generator() {
+    use gen::local::generate;
+    use std::rc::Rc;
+
+    // Rc items and state are fine; the generator never leaves this thread.
+    let shared = Rc::new(5);
+    let iter = generate(|co| async move {
+        for i in 0..3 {
+            co.yield_(Rc::new(*shared + i)).await;
+        }
+        Rc::strong_count(&shared)
+    });
+
+    assert_eq!(iter.map(|rc| *rc).collect::<Vec<_>>(), [5, 6, 7]);
+}
+
+#[test]
+fn local_generator_bidi() {
+    use gen::local::generate_bidi;
+
+    let mut generator = generate_bidi(|co| async move {
+        let mut total = 0;
+        loop {
+            total += co.yield_(total).await;
+        }
+    });
+
+    assert_eq!(generator.send(0), Some(0));
+    assert_eq!(generator.send(3), Some(3));
+    assert_eq!(generator.send(4), Some(7));
+}
+
+#[test]
+fn scoped_storage_reuse() {
+    use gen::scoped::{generate, Storage};
+
+    let mut storage = Storage::new();
+    let mut iter = generate(&mut storage, |co| async move {
+        co.yield_(1).await;
+        co.yield_(2).await;
+    });
+    assert_eq!(iter.next(), Some(1));
+    drop(iter);
+
+    let iter = generate(&mut storage, |co| async move {
+        co.yield_(3).await;
+        co.yield_(4).await;
+    });
+    assert_eq!(iter.collect::<Vec<_>>(), [3, 4]);
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn generator_is_send_and_sync() {
+    use gen::{generate, generate_bidi};
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let generator = generate(|co| async move {
+        co.yield_(1).await;
+        "done"
+    });
+    assert_send_sync(&generator);
+    let generator = generate_bidi(|co| async move {
+        let resume: String = co.yield_(1).await;
+        resume
+    });
+    assert_send_sync(&generator);
+    #[cfg(feature = "std")]
+    assert_send_sync(&gen::generate_catching(|co| async move {
+        co.yield_(1).await;
+    }));
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn shared_state_across_threads() {
+    use gen::generate;
+    use std::thread;
+
+    // Threads contend for the shared state's lock through clones of co
+    let mut generator = generate(|co| async move {
+        thread::scope(|s| {
+            for t in 0..4 {
+                let co = co.clone();
+                s.spawn(move || {
+                    for i in 0..250 {
+                        co.annotate(format!("{t}-{i}"), "");
+                    }
+                });
+            }
+        });
+        co.yield_(()).await;
+    });
+
+    assert_eq!(generator.next(), Some(()));
+    assert_eq!(generator.metadata().len(), 1000);
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn scoped_move_to_thread() {
+    use gen::scoped::{generate, Storage};
+    use std::thread;
+
+    let mut storage = Storage::new();
+    let mut iter = generate(&mut storage, |co| async move {
+        for i in 0.. {
+            co.yield_(i).await;
+        }
+    });
+    assert_eq!(iter.next(), Some(0));
+    thread::scope(move |s| {
+        s.spawn(move || assert_eq!(iter.next(), Some(1)));
+    });
+}
+
+#[test]
+fn drop_future_when_finished() {
+    use gen::generate;
+    use std::rc::Rc;
+
+    let captured = Rc::new(());
+    let weak = Rc::downgrade(&captured);
+    let mut iter = gen::local::generate(|co| async move {
+        let _captured = captured;
+        co.yield_(0).await;
+    });
+
+    assert!(!iter.is_finished());
+    assert_eq!(iter.next(), Some(0));
+    assert!(weak.upgrade().is_some());
+    assert_eq!(iter.next(), None);
+    assert!(iter.is_finished());
+    assert!(weak.upgrade().is_none());
+
+    let mut iter = generate(|co| async move { co.yield_(0).await });
+    assert_eq!(iter.next(), Some(0));
+    iter.close();
+    assert!(iter.is_finished());
+}
+
+#[test]
+fn stack_generator() {
+    use gen::scoped::{generate_bidi_on_stack, generate_on_stack, Storage};
+
+    let mut storage = Storage::new();
+    let items = generate_on_stack(
+        &mut storage,
+        |co| async move {
+            for i in 0..5 {
+                co.yield_(i * i).await;
+            }
+        },
+        |iter| iter.collect::<Vec<_>>(),
+    );
+    assert_eq!(items, [0, 1, 4, 9, 16]);
+
+    let mut storage = Storage::new();
+    generate_bidi_on_stack(
+        &mut storage,
+        |co| async move {
+            let mut total = 0;
+            loop {
+                total += co.yield_(total).await;
+            }
+        },
+        |mut generator| {
+            assert_eq!(generator.send(0), Some(0));
+            assert_eq!(generator.send(2), Some(2));
+            assert_eq!(generator.send(3), Some(5));
+        },
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stack_generator_reuse_allocates_nothing() {
+    use gen::scoped::{generate_on_stack, Storage};
+
+    let mut storage = Storage::new();
+    let sum = |storage: &mut Storage<i32>| {
+        generate_on_stack(
+            storage,
+            |co| async move {
+                for i in 0..4 {
+                    co.yield_(i).await;
+                }
+            },
+            |iter| iter.sum::<i32>(),
+        )
+    };
+
+    // The first use allocates the storage's item queue
+    assert_eq!(
+        counting::allocations(|| assert_eq!(sum(&mut storage), 6)),
+        1
+    );
+    assert_eq!(
+        counting::allocations(|| assert_eq!(sum(&mut storage), 6)),
+        0
+    );
+}
+
+#[test]
+fn try_generate_error_is_last_item() {
+    use gen::try_generate;
+
+    let mut iter = try_generate(|co| async move {
+        co.yield_(1).await;
+        co.yield_(2).await;
+        Err("failed")?;
+        co.yield_(3).await;
+        Ok(())
+    });
+
+    assert_eq!(iter.next(), Some(Ok(1)));
+    assert_eq!(iter.next(), Some(Ok(2)));
+    assert_eq!(iter.next(), Some(Err("failed")));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn try_generate_success() {
+    use gen::try_generate;
+
+    let mut iter = try_generate(|co| async move {
+        co.yield_(1).await;
+        Ok::<_, String>("finished")
+    });
+
+    assert_eq!(iter.next(), Some(Ok(1)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.into_generator().into_return(), Some(Ok("finished")));
+}
+
+#[test]
+fn terminal_error_separate_from_items() {
+    use gen::generate;
+
+    let mut iter = generate(|co| async move {
+        for i in 0..3 {
+            co.yield_(i).await;
+        }
+        if true {
+            return Err("truncated");
+        }
+        Ok(3)
+    });
+
+    assert_eq!(iter.error(), None);
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(iter.error(), Some(&"truncated"));
+    assert_eq!(iter.into_result(), Some(Err("truncated")));
+
+    let mut iter = generate(|co| async move {
+        co.yield_(0).await;
+        Ok::<_, ()>(1)
+    });
+    assert_eq!(iter.by_ref().count(), 1);
+    assert_eq!(iter.error(), None);
+    assert_eq!(iter.into_result(), Some(Ok(1)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn catch_panic() {
+    use gen::{generate, generate_catching};
+
+    let mut iter = generate_catching(|co| async move {
+        co.yield_(1).await;
+        co.yield_(2).await;
+        panic!("oops");
+    });
+    assert!(iter.panic_payload().is_none());
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [1, 2]);
+    assert!(iter.is_finished());
+    assert!(iter.is_poisoned());
+    assert_eq!(iter.next(), None);
+    let payload = iter.take_panic_payload().unwrap();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"oops"));
+
+    // Catching can be enabled after creation
+    let mut iter = generate(|co| async move {
+        co.yield_(1).await;
+        let v: Vec<i32> = Vec::new();
+        v[0]
+    });
+    iter.set_catch_panics(true);
+    assert_eq!(iter.by_ref().count(), 1);
+    assert!(iter.panic_payload().is_some());
+}
+
+#[test]
+fn poisoned_after_panic() {
+    use gen::generate;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut iter = generate(|co| async move {
+        co.yield_(1).await;
+        panic!("oops");
+    });
+    assert_eq!(iter.next(), Some(1));
+    assert!(!iter.is_poisoned());
+
+    assert!(catch_unwind(AssertUnwindSafe(|| iter.next())).is_err());
+    assert!(iter.is_poisoned());
+    assert!(iter.is_finished());
+    assert_eq!(iter.next(), None);
+    assert!(catch_unwind(AssertUnwindSafe(|| iter.resume())).is_err());
+}
+
+#[test]
+fn foreign_future_that_wakes_itself() {
+    use gen::generate;
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    // Pending a few times, waking itself each time
+    struct Countdown(u32);
+    impl Future for Countdown {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                Poll::Ready(())
+            } else {
+                self.0 -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    let iter = generate(|co| async move {
+        co.yield_(1).await;
+        Countdown(3).await;
+        co.yield_(2).await;
+    });
+    assert_eq!(iter.collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "awaited a future other than Communication::yield_")]
+fn foreign_future_never_woken() {
+    use gen::generate;
+
+    let mut iter = generate(|co| async move {
+        co.yield_(1).await;
+        std::future::pending::<()>().await;
+        co.yield_(2).await;
+    });
+    assert_eq!(iter.next(), Some(1));
+    iter.next();
+}
+
+#[test]
+fn driver_wakes_from_other_thread() {
+    use gen::generate_with_driver;
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{mpsc, Arc},
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    // A minimal block_on: park until woken
+    struct Unparker(Thread);
+    impl Wake for Unparker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let driver = |mut future: Pin<&mut dyn Future<Output = ()>>| {
+        let waker = Waker::from(Arc::new(Unparker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        while future.as_mut().poll(&mut cx).is_pending() {
+            thread::park();
+        }
+    };
+
+    // Resolves once another thread sends a value, waking the generator
+    struct Recv(mpsc::Receiver<i32>, Option<mpsc::Sender<Waker>>);
+    impl Future for Recv {
+        type Output = i32;
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<i32> {
+            if let Ok(value) = self.0.try_recv() {
+                return Poll::Ready(value);
+            }
+            if let Some(waker_tx) = self.1.take() {
+                waker_tx.send(cx.waker().clone()).unwrap();
+            }
+            Poll::Pending
+        }
+    }
+
+    let mut iter = generate_with_driver(driver, |co| async move {
+        for i in 0..3 {
+            let (value_tx, value_rx) = mpsc::channel();
+            let (waker_tx, waker_rx) = mpsc::channel::<Waker>();
+            let sender = thread::spawn(move || {
+                let waker = waker_rx.recv().unwrap();
+                value_tx.send(i * 10).unwrap();
+                waker.wake();
+            });
+            let value = Recv(value_rx, Some(waker_tx)).await;
+            sender.join().unwrap();
+            co.yield_(value).await;
+        }
+        "done"
+    });
+
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [0, 10, 20]);
+    assert_eq!(iter.return_value(), Some(&"done"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sleep_paces_items() {
+    use gen::generate;
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let iter = generate(|co| async move {
+        for i in 0..3 {
+            co.sleep(Duration::from_millis(10)).await;
+            co.yield_(i).await;
+        }
+    });
+    assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn timeout_wakes_from_other_thread() {
+    use gen::{generate, TimedOut};
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{mpsc, Arc, Mutex},
+        task::{Context, Poll, Waker},
+        thread,
+        time::Duration,
+    };
+
+    // Resolves once another thread sets the value and wakes it
+    struct Shared(Arc<Mutex<(Option<i32>, Option<Waker>)>>);
+    impl Future for Shared {
+        type Output = i32;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<i32> {
+            let mut lock = self.0.lock().unwrap();
+            match lock.0 {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    lock.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    let iter = generate(|co| async move {
+        let state = Arc::new(Mutex::new((None, None::<Waker>)));
+        let (started_tx, started_rx) = mpsc::channel();
+        let setter = state.clone();
+        let handle = thread::spawn(move || {
+            started_rx.recv().unwrap();
+            thread::sleep(Duration::from_millis(5));
+            let mut lock = setter.lock().unwrap();
+            lock.0 = Some(7);
+            if let Some(waker) = lock.1.take() {
+                waker.wake();
+            }
+        });
+        started_tx.send(()).unwrap();
+        let result = co
+            .timeout(Duration::from_secs(10), Shared(state.clone()))
+            .await;
+        handle.join().unwrap();
+        co.yield_(result).await;
+
+        let result = co
+            .timeout(Duration::from_millis(5), std::future::pending::<i32>())
+            .await;
+        co.yield_(result).await;
+    });
+
+    assert_eq!(iter.collect::<Vec<_>>(), [Ok(7), Err(TimedOut)]);
+}
+
+#[test]
+fn yield_all() {
+    use gen::generate;
+
+    let words = ["a", "b", "c"];
+    let iter = generate(|co| async move {
+        co.yield_all(words.iter().map(|w| w.to_uppercase())).await;
+        co.yield_all(Vec::new()).await;
+        co.yield_("D".to_owned()).await;
+    });
+    assert_eq!(iter.collect::<Vec<_>>(), ["A", "B", "C", "D"]);
+}
+
+#[test]
+fn yield_from_sub_generator() {
+    use gen::generate_bidi;
+
+    // The sub-generator sees the resume values too
+    let mut generator = generate_bidi(|co| async move {
+        let sum = co
+            .yield_from(|co| async move {
+                let mut sum = 0;
+                for i in 0..3 {
+                    sum += co.yield_(i).await;
+                }
+                sum
+            })
+            .await;
+        co.yield_(sum).await;
+    });
+
+    assert_eq!(generator.send(0), Some(0));
+    assert_eq!(generator.send(10), Some(1));
+    assert_eq!(generator.send(20), Some(2));
+    assert_eq!(generator.send(30), Some(60));
+    assert_eq!(generator.send(0), None);
+}
+
+#[test]
+fn concurrent_yields() {
+    use gen::generate_bidi;
+    use std::{
+        future::{poll_fn, Future},
+        pin::pin,
+        task::Poll,
+    };
+
+    // Each yield_ receives the value sent after its own item
+    let mut generator = generate_bidi(|co| async move {
+        let other = co.clone();
+        let mut a = pin!(async move { co.yield_('a').await + co.yield_('b').await });
+        let mut b = pin!(async move { other.yield_('x').await });
+        let (mut a_sum, mut b_sum) = (None, None);
+        poll_fn(|cx| {
+            if a_sum.is_none() {
+                a_sum = match a.as_mut().poll(cx) {
+                    Poll::Ready(sum) => Some(sum),
+                    Poll::Pending => None,
+                };
+            }
+            if b_sum.is_none() {
+                b_sum = match b.as_mut().poll(cx) {
+                    Poll::Ready(sum) => Some(sum),
+                    Poll::Pending => None,
+                };
+            }
+            match (a_sum, b_sum) {
+                (Some(a), Some(b)) => Poll::Ready((a, b)),
+                _ => Poll::Pending,
+            }
+        })
+        .await
+    });
+
+    assert_eq!(generator.send(0), Some('a'));
+    assert_eq!(generator.send(1), Some('x'));
+    assert_eq!(generator.send(10), Some('b'));
+    assert_eq!(generator.send(100), None);
+    assert_eq!(generator.into_return(), Some((101, 10)));
+}
+
+#[test]
+fn close_concurrent_yields() {
+    use gen::{generate, Communication, DropPolicy};
+    use std::{
+        cell::Cell,
+        future::{poll_fn, Future},
+        pin::Pin,
+        task::Poll,
+    };
+
+    // Three clones of co yield at once, so closing after the first
+    // item skips two
+    async fn yield_three(co: Communication<i32>) -> bool {
+        let mut yields: Vec<Option<Pin<Box<dyn Future<Output = ()>>>>> = (0..3)
+            .map(|i| {
+                let co = co.clone();
+                Some(Box::pin(async move { co.yield_(i).await })
+                    as Pin<Box<dyn Future<Output = ()>>>)
+            })
+            .collect();
+        poll_fn(|cx| {
+            for slot in &mut yields {
+                if slot
+                    .as_mut()
+                    .is_some_and(|y| y.as_mut().poll(cx).is_ready())
+                {
+                    *slot = None;
+                }
+            }
+            match yields.iter().all(Option::is_none) {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await;
+        co.is_closed()
+    }
+
+    let mut generator = generate(yield_three);
+    assert_eq!(generator.next(), Some(0));
+    generator.close();
+    assert_eq!(generator.into_return(), Some(true));
+
+    let finished = &Cell::new(false);
+    let mut generator = generate(|co| async move {
+        finished.set(yield_three(co).await);
+    });
+    generator.set_drop_policy(DropPolicy::Close);
+    assert_eq!(generator.next(), Some(0));
+    drop(generator);
+    assert!(finished.get());
+}
+
+#[test]
+fn buffered_return_after_drain() {
+    use gen::generate_buffered;
+
+    // It returns with items still queued
+    let mut generator = generate_buffered(8, |co| async move {
+        co.yield_all(0..3).await;
+        "done"
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    assert!(!generator.is_finished());
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [1, 2]);
+    assert!(generator.is_finished());
+    assert_eq!(generator.return_value(), Some(&"done"));
+}
+
+#[test]
+fn close_buffered_runs_cleanup() {
+    use gen::{generate_buffered, DropPolicy};
+    use std::cell::Cell;
+
+    let cleaned_up = &Cell::new(false);
+    let mut generator = generate_buffered(4, |co| async move {
+        let mut i = 0;
+        while !co.is_closed() {
+            co.yield_(i).await;
+            i += 1;
+        }
+        cleaned_up.set(true);
+        i
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    generator.close();
+    assert!(cleaned_up.get());
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.into_return(), Some(4));
+
+    cleaned_up.set(false);
+    let mut generator = generate_buffered(4, |co| async move {
+        while !co.is_closed() {
+            co.yield_(0).await;
+        }
+        cleaned_up.set(true);
+    });
+    generator.set_drop_policy(DropPolicy::Close);
+    assert_eq!(generator.next(), Some(0));
+    drop(generator);
+    assert!(cleaned_up.get());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn prefetch_drop_stops_thread() {
+    use gen::generate_prefetch;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    struct SetOnDrop(Arc<AtomicBool>);
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = SetOnDrop(dropped.clone());
+    let mut iter = generate_prefetch(2, |co| async move {
+        let _guard = guard;
+        for i in 0.. {
+            co.yield_(i).await;
+        }
+    });
+
+    assert_eq!(iter.by_ref().take(3).collect::<Vec<_>>(), [0, 1, 2]);
+    drop(iter);
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic(expected = "decode failed")]
+fn prefetch_panic_propagates() {
+    use gen::generate_prefetch;
+
+    let iter = generate_prefetch(4, |co| async move {
+        co.yield_(1).await;
+        panic!("decode failed");
+    });
+    for _ in iter {}
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn generator_attribute() {
+    #[gen::generator]
+    fn fib(n: usize) -> u64 {
+        let (mut a, mut b) = (0, 1);
+        for _ in 0..n {
+            yield_!(a);
+            (a, b) = (b, a + b);
+        }
+    }
+
+    #[gen::generator]
+    fn words<'a, T: AsRef<str> + ?Sized>(text: &'a T) -> &'a str {
+        for word in text.as_ref().split(' ') {
+            if word.is_empty() {
+                return;
+            }
+            yield_!(word);
+        }
+    }
+
+    assert_eq!(fib(7).collect::<Vec<_>>(), [0, 1, 1, 2, 3, 5, 8]);
+    let text = String::from("borrowed from the caller  ignored");
+    assert_eq!(
+        words(&text).collect::<Vec<_>>(),
+        ["borrowed", "from", "the", "caller"]
+    );
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn gen_macro() {
+    use gen::gen;
+
+    let xs = vec![10, 20];
+    let iter = gen! {
+        yield 1;
+        for x in xs {
+            if x > 10 {
+                yield x * 2
+            } else {
+                yield x;
+            }
+        }
+    };
+    assert_eq!(iter.collect::<Vec<_>>(), [1, 10, 40]);
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn gen_macro_match_arms() {
+    use gen::gen;
+
+    let iter = gen! {
+        for x in 0..3 {
+            match x {
+                1 => yield 10,
+                2 => yield Vec::<(u8, u8)>::new().len() + 22,
+                _ => yield x
+            }
+        }
+    };
+    assert_eq!(iter.collect::<Vec<_>>(), [0, 10, 22]);
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn boxed_keeps_state() {
+    use gen::{generate_bidi, BoxGenerator, DropPolicy};
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let cleaned_up = Arc::new(AtomicBool::new(false));
+    let flag = cleaned_up.clone();
+    let mut generator = generate_bidi(move |co| async move {
+        let mut total = 0;
+        while !co.is_closed() {
+            total += co.yield_(total).await;
+        }
+        flag.store(true, Ordering::SeqCst);
+        total
+    });
+    generator.set_drop_policy(DropPolicy::Close);
+    assert_eq!(generator.send(0), Some(0));
+
+    let mut boxed: BoxGenerator<i32, i32, i32> = generator.boxed();
+    assert_eq!(boxed.send(5), Some(5));
+    drop(boxed);
+    assert!(cleaned_up.load(Ordering::SeqCst));
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn async_generator_for_every_backend() {
+    use gen::{generate, scoped, AsyncGenerator};
+
+    fn drain<G: AsyncGenerator<u8, Resume = (), Return = &'static str>>(
+        mut generator: G,
+    ) -> (usize, Option<&'static str>) {
+        let mut count = 0;
+        while generator.send(()).is_some() {
+            count += 1;
+        }
+        assert!(generator.is_finished());
+        (count, generator.return_value().copied())
+    }
+
+    assert_eq!(
+        drain(generate(|co| async move {
+            co.yield_all([1, 2]).await;
+            "sync"
+        })),
+        (2, Some("sync"))
+    );
+    assert_eq!(
+        drain(
+            generate(|co| async move {
+                co.yield_(1).await;
+                "boxed"
+            })
+            .boxed()
+        ),
+        (1, Some("boxed"))
+    );
+    let mut storage = scoped::Storage::new();
+    assert_eq!(
+        scoped::generate_on_stack(
+            &mut storage,
+            |co| async move {
+                co.yield_all([1, 2, 3]).await;
+                "stack"
+            },
+            |generator| drain(generator)
+        ),
+        (3, Some("stack"))
+    );
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn genawaiter_compat() {
+    use gen::genawaiter::{rc, stack, sync, GeneratorState};
+
+    let mut boxed = sync::GenBoxed::new_boxed(|co| async move {
+        let doubled = co.yield_(1).await * 2;
+        co.yield_(doubled).await;
+    });
+    assert_eq!(boxed.resume_with(0), GeneratorState::Yielded(1));
+    assert_eq!(boxed.resume_with(21), GeneratorState::Yielded(42));
+    assert_eq!(boxed.resume_with(0), GeneratorState::Complete(()));
+    // Boxed once, as a BoxGenerator
+    let _: gen::BoxGenerator<'static, i32, (), i32> = boxed.into_generator();
+
+    let counter = rc::Gen::new(|co| async move {
+        for i in 0..3 {
+            co.yield_(i).await;
+        }
+    });
+    assert_eq!(counter.into_iter().collect::<Vec<_>>(), [0, 1, 2]);
+
+    let mut shelf = stack::Shelf::new();
+    let mut generator = stack::Gen::new(&mut shelf, |co| async move {
+        co.yield_("a").await;
+        5
+    });
+    assert_eq!(generator.resume(), GeneratorState::Yielded("a"));
+    assert_eq!(generator.resume(), GeneratorState::Complete(5));
+}
+
+#[test]
+fn lending_reuses_buffer() {
+    use gen::{generate_lending, LendingIterator};
+
+    let mut chunks = generate_lending(|co| async move {
+        let mut buffer = Vec::with_capacity(4);
+        for chunk in [[1, 2], [3, 4], [5, 6]] {
+            buffer.clear();
+            buffer.extend(chunk);
+            buffer = co.yield_ref(buffer).await;
+        }
+    });
+
+    let first = chunks.next().unwrap();
+    assert_eq!(first[..], [1, 2]);
+    let address = first.as_ptr();
+    let mut sums = Vec::new();
+    while let Some(chunk) = chunks.next() {
+        assert_eq!(chunk.as_ptr(), address);
+        sums.push(chunk.iter().sum::<i32>());
+    }
+    assert_eq!(sums, [7, 11]);
+}
+
+#[test]
+fn drive_to_completion() {
+    use gen::drive;
+    use std::ops::ControlFlow;
+
+    let mut seen = Vec::new();
+    let result = drive(
+        |co| async move {
+            co.yield_all(["a", "b"]).await;
+            2
+        },
+        |item| {
+            seen.push(item);
+            ControlFlow::<()>::Continue(())
+        },
+    );
+    assert_eq!(result, ControlFlow::Continue(2));
+    assert_eq!(seen, ["a", "b"]);
+}
+
+#[test]
+fn size_hint_counts_buffered_items() {
+    use gen::generate_buffered;
+
+    let mut iter = generate_buffered(3, |co| async move {
+        co.set_remaining(5);
+        co.yield_all(0..5).await;
+    });
+    assert_eq!(iter.next(), Some(0));
+    // Two queued, two still to yield
+    assert_eq!(iter.size_hint(), (4, Some(4)));
+    assert_eq!(iter.by_ref().count(), 4);
+    assert_eq!(iter.size_hint(), (0, Some(0)));
+
+    // A huge promise made with items already queued
+    let mut iter = generate_buffered(3, |co| async move {
+        co.yield_all([0, 1]).await;
+        co.set_remaining(usize::MAX);
+        co.yield_all(2..).await;
+    });
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.size_hint(), (usize::MAX, None));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "generate_exact yielded too few items")]
+fn exact_too_few() {
+    use gen::generate_exact;
+
+    let iter = generate_exact(3, |co| async move {
+        co.yield_(1).await;
+    });
+    for _ in iter {}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn fused_after_completion() {
+    use gen::{generate_catching, try_generate};
+    use std::iter::FusedIterator;
+
+    fn drain_twice<I: FusedIterator>(mut iter: I) -> usize {
+        let count = iter.by_ref().count();
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+        count
+    }
+
+    assert_eq!(
+        drain_twice(generate_catching(|co| async move {
+            co.yield_(1).await;
+            panic!("stop");
+        })),
+        1
+    );
+    assert_eq!(
+        drain_twice(try_generate(|co| async move {
+            co.yield_(1).await;
+            Err::<(), _>("failed")
+        })),
+        2
+    );
+}
+
+#[test]
+fn peekable_keeps_generator() {
+    use gen::generate;
+
+    let mut iter = generate(|co| async move {
+        co.yield_(1).await;
+        "end"
+    })
+    .peekable();
+
+    assert_eq!(iter.peek(), Some(&1));
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.peek(), None);
+    assert!(iter.generator().is_finished());
+    let (generator, peeked) = iter.into_parts();
+    assert_eq!(peeked, None);
+    assert_eq!(generator.into_return(), Some("end"));
+
+    let mut iter = generate(|co| async move {
+        let mut i = 0;
+        while !co.is_closed() {
+            co.yield_(i).await;
+            i += 1;
+        }
+        i
+    })
+    .peekable();
+    assert_eq!(iter.peek(), Some(&0));
+    iter.generator_mut().close();
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.generator().return_value(), Some(&1));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn state_introspection() {
+    use gen::{generate_catching, Status};
+
+    let mut generator = generate_catching(|co| async move {
+        co.yield_all([1, 2]).await;
+        panic!("oops");
+    });
+    assert_eq!(generator.state(), Status::NotStarted);
+    generator.next();
+    assert_eq!(generator.state(), Status::Suspended);
+    generator.next();
+    assert_eq!(generator.items_so_far(), 2);
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.state(), Status::Poisoned);
+
+    let mut generator = generate_catching(|co| async move { co.yield_(1).await });
+    generator.close();
+    assert_eq!(generator.state(), Status::Completed);
+    assert_eq!(generator.items_so_far(), 0);
+}
+
+#[test]
+fn debug_output() {
+    use gen::generate;
+
+    struct NotDebug;
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Holder<G>(G);
+
+    let mut generator = generate(|co| async move {
+        co.yield_(NotDebug).await;
+    });
+    generator.next();
+    let shown = format!("{:?}", Holder(&generator));
+    assert!(shown.starts_with("Holder(Generator { started: true, done: false"));
+    assert!(shown.contains("items_so_far: 1"));
+}
+
+#[test]
+fn statistics_counters() {
+    use gen::generate_buffered;
+    use std::{future::poll_fn, task::Poll};
+
+    let mut generator = generate_buffered(4, |co| async move {
+        // Pending once, after waking itself
+        let mut ready = false;
+        poll_fn(|cx| match std::mem::replace(&mut ready, true) {
+            true => Poll::Ready(()),
+            false => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        co.yield_all(0..6).await;
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    assert_eq!(generator.polls(), 2);
+    assert_eq!(generator.resumes_without_yield(), 1);
+    assert_eq!(generator.items_yielded(), 4);
+    assert_eq!(generator.items_so_far(), 1);
+    assert_eq!(generator.by_ref().count(), 5);
+    assert_eq!(generator.items_yielded(), 6);
+}
+
+#[test]
+fn parts_round_trip() {
+    use gen::{generate, Generator};
+
+    let mut generator = generate(|co| async move {
+        co.yield_all([1, 2, 3]).await;
+        "end"
+    });
+    assert_eq!(generator.next(), Some(1));
+
+    let (future, shared) = generator.into_parts();
+    let mut generator = Generator::from_parts(future, shared);
+    assert_eq!(generator.items_so_far(), 1);
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [2, 3]);
+    assert_eq!(generator.return_value(), Some(&"end"));
+
+    let (future, shared) = generator.into_parts();
+    assert!(future.is_none());
+    assert!(Generator::from_parts(future, shared).is_finished());
+}
+
+#[test]
+fn poll_next_with_own_waker() {
+    use gen::generate;
+    use std::{
+        future::poll_fn,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    struct CountWakes(AtomicUsize);
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // Waits for one wake, then yields
+    let mut generator = generate(|co| async move {
+        let mut waited = false;
+        poll_fn(|cx| {
+            if std::mem::replace(&mut waited, true) {
+                Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        co.yield_("ready").await;
+    });
+
+    let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+    let waker = Waker::from(wakes.clone());
+    let mut cx = Context::from_waker(&waker);
+    assert_eq!(generator.poll_next(&mut cx), Poll::Pending);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    assert_eq!(generator.poll_next(&mut cx), Poll::Ready(Some("ready")));
+}
+
+#[test]
+fn budget_stops_at_unwoken_future() {
+    use gen::{generate, Budgeted};
+    use std::{
+        future::poll_fn,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        task::Poll,
+    };
+
+    // Pending until another party sets the flag, without waking
+    let ready = Arc::new(AtomicBool::new(false));
+    let flag = ready.clone();
+    let mut generator = generate(|co| async move {
+        poll_fn(|_| match flag.load(Ordering::SeqCst) {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        })
+        .await;
+        co.yield_(1).await;
+    });
+
+    assert_eq!(generator.next_with_budget(100), Budgeted::Exhausted);
+    assert!(generator.polls() <= 2);
+    ready.store(true, Ordering::SeqCst);
+    assert_eq!(generator.next_with_budget(100), Budgeted::Item(1));
+}
+
+#[test]
+fn scheduler_mixes_backends() {
+    use gen::{generate, local, scheduler::Scheduler};
+
+    let mut scheduler = Scheduler::new();
+    let forever = scheduler.spawn(generate(|co| async move {
+        loop {
+            co.yield_(0).await;
+        }
+    }));
+    let local = scheduler.spawn(local::generate(|co| async move {
+        co.yield_all([1, 2]).await;
+    }));
+
+    let items = scheduler.run_for(6);
+    assert_eq!(items.iter().filter(|(id, _)| *id == local).count(), 2);
+    assert_eq!(scheduler.len(), 1);
+    assert!(scheduler.cancel(forever));
+    assert!(!scheduler.cancel(forever));
+    assert!(scheduler.is_empty());
+    assert!(scheduler.run_for(10).is_empty());
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn merge_is_lazy() {
+    use gen::{generate, merge};
+
+    let evens = generate(|co| async move {
+        for i in (0..).step_by(2) {
+            co.yield_(i).await;
+        }
+    });
+    let odds = generate(|co| async move {
+        for i in (1..).step_by(2) {
+            co.yield_(i).await;
+        }
+    });
+    let merged: Vec<_> = merge([evens.boxed(), odds.boxed()]).take(5).collect();
+    assert_eq!(merged, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn merge_sorted_ties_and_empty() {
+    use gen::merge_sorted_by_key;
+
+    let merged = merge_sorted_by_key(
+        [vec![(1, 'a'), (2, 'a')], vec![], vec![(1, 'b'), (3, 'b')]].map(Vec::into_iter),
+        |(key, _)| *key,
+    );
+    assert_eq!(
+        merged.collect::<Vec<_>>(),
+        [(1, 'a'), (1, 'b'), (2, 'a'), (3, 'b')]
+    );
+}
+
+#[cfg(any(feature = "std", feature = "spin"))]
+#[test]
+fn flatten_inner_return_values() {
+    use gen::generate;
+
+    let batch = |n: u8| {
+        generate(move |co| async move {
+            co.yield_all(0..n).await;
+            n
+        })
+        .boxed()
+    };
+    let mut flat = generate(|co| async move {
+        co.yield_(batch(2)).await;
+        co.yield_(batch(1)).await;
+    })
+    .flatten();
+
+    assert_eq!(flat.next(), Some(0));
+    assert_eq!(flat.next(), Some(1));
+    assert_eq!(flat.inner().unwrap().return_value(), None);
+    assert_eq!(flat.next(), Some(0));
+    assert_eq!(flat.next(), None);
+    assert_eq!(flat.inner().unwrap().return_value(), Some(&1));
+}
+
+#[test]
+fn yield_now_with_plain_next() {
+    use gen::generate;
+
+    let mut generator = generate(|co| async move {
+        co.yield_now().await;
+        co.yield_now().await;
+        co.yield_(1).await;
+    });
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.resumes_without_yield(), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn prefetch_next_timeout() {
+    use gen::{generate_prefetch, TimedOut};
+    use std::{sync::mpsc, time::Duration};
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let mut iter = generate_prefetch(1, move |co| async move {
+        co.yield_(1).await;
+        // Blocks the producer thread until the test says go
+        rx.recv().unwrap();
+        co.yield_(2).await;
+    });
+
+    assert_eq!(iter.next_timeout(Duration::from_secs(5)), Ok(Some(1)));
+    assert_eq!(iter.next_timeout(Duration::from_millis(10)), Err(TimedOut));
+    tx.send(()).unwrap();
+    assert_eq!(iter.next_timeout(Duration::from_secs(5)), Ok(Some(2)));
+    assert_eq!(iter.next_timeout(Duration::from_secs(5)), Ok(None));
+}
+
+#[test]
+fn cancelled_on_close() {
+    use gen::{generate, Budgeted, Communication};
+
+    let mut generator = generate(|co: Communication<()>| async move {
+        // Nothing wakes this, but close polls again
+        co.cancelled().await;
+        co.is_cancelled()
+    });
+    assert_eq!(generator.next_with_budget(1), Budgeted::Exhausted);
+    generator.close();
+    assert_eq!(generator.return_value(), Some(&true));
+}
+
+#[test]
+fn cancelled_releases_wakers() {
+    use gen::{generate, CancellationToken, Communication};
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Weak,
+        },
+        task::{Context, Wake, Waker},
+    };
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    // Poll `cancelled` once with a fresh waker, returning a handle to it
+    fn poll_once(cancelled: std::pin::Pin<&mut impl Future>) -> (Weak<Flag>, Arc<Flag>) {
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        assert!(cancelled
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        (Arc::downgrade(&flag), flag)
+    }
+
+    let token = CancellationToken::new();
+    let mut generator = generate(|co: Communication<()>| async move {
+        // Dropped futures let go of their wakers
+        for _ in 0..100 {
+            let (weak, flag) = poll_once(pin!(co.cancelled()));
+            drop(flag);
+            assert!(weak.upgrade().is_none());
+        }
+
+        // Polling with a new waker replaces the old one
+        let mut cancelled = pin!(co.cancelled());
+        let (first, flag) = poll_once(cancelled.as_mut());
+        drop(flag);
+        let (_, second) = poll_once(cancelled.as_mut());
+        assert!(first.upgrade().is_none());
+        co.yield_(()).await;
+        second.0.load(Ordering::SeqCst)
+    });
+    generator.set_cancellation_token(token.clone());
+
+    assert_eq!(generator.next(), Some(()));
+    token.cancel();
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.return_value(), Some(&true));
+}
+
+#[test]
+fn on_cancel() {
+    use gen::generate;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let hook = |runs: &Arc<AtomicUsize>| {
+        let runs = runs.clone();
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+
+    // Completing discards the callbacks
+    let mut generator = generate(|co| {
+        let hook = hook(&runs);
+        async move {
+            co.on_cancel(hook);
+            co.yield_(1).await;
+        }
+    });
+    generator.on_cancel(hook(&runs));
+    assert_eq!(generator.by_ref().count(), 1);
+    drop(generator);
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+    // Dropping early runs them
+    let mut generator = generate(|co| {
+        let hook = hook(&runs);
+        async move {
+            co.on_cancel(hook);
+            co.yield_(1).await;
+        }
+    });
+    generator.on_cancel(hook(&runs));
+    assert_eq!(generator.next(), Some(1));
+    drop(generator);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn complete_on_drop() {
+    use gen::{generate, DropPolicy};
+    use std::cell::Cell;
+
+    let flushed = &Cell::new(0);
+    let mut generator = generate(|co| async move {
+        for i in 0..5 {
+            co.yield_(i).await;
+        }
+        flushed.set(flushed.get() + 1);
+    });
+    generator.set_drop_policy(DropPolicy::Complete);
+
+    // Also survives the move into a boxed generator
+    let generator = generator.boxed_local();
+    assert_eq!(generator.take(2).collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(flushed.get(), 1);
+}
+
+#[test]
+fn restartable() {
+    use gen::generate_restartable;
+
+    let mut runs = 0;
+    let mut generator = generate_restartable(|co| {
+        runs += 1;
+        let run = runs;
+        async move {
+            co.yield_(run).await;
+            co.yield_(run * 10).await;
+        }
+    });
+
+    assert_eq!(generator.next(), Some(1));
+    generator.restart();
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [2, 20]);
+    assert!(generator.generator().is_finished());
+    generator.restart();
+    assert_eq!(generator.next(), Some(3));
+}
+
+#[test]
+fn tee() {
+    use gen::generate;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let runs = &AtomicUsize::new(0);
+    let (mut left, mut right) = generate(|co| async move {
+        runs.fetch_add(1, Ordering::SeqCst);
+        co.yield_all(["a", "b", "c"]).await;
+    })
+    .tee();
+
+    assert_eq!(left.next(), Some("a"));
+    assert_eq!(left.next(), Some("b"));
+    assert_eq!(right.size_hint().0, 2);
+    assert_eq!(right.next(), Some("a"));
+    assert_eq!(right.next(), Some("b"));
+    assert_eq!(right.next(), Some("c"));
+    drop(right);
+    assert_eq!(left.next(), Some("c"));
+    assert_eq!(left.next(), None);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn memoize() {
+    use gen::generate;
+    use std::cell::Cell;
+
+    let resumes = &Cell::new(0);
+    let mut memoized = generate(|co| async move {
+        for i in 0..3 {
+            resumes.set(resumes.get() + 1);
+            co.yield_(i).await;
+        }
+    })
+    .memoize();
+
+    assert_eq!(memoized.iter().next(), Some(0));
+    assert_eq!(memoized.iter().next(), Some(0));
+    assert_eq!(resumes.get(), 1);
+    assert_eq!(memoized.iter().size_hint().0, 1);
+    assert_eq!(memoized.iter().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(memoized.iter().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(resumes.get(), 3);
+    assert!(memoized.generator().is_finished());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn generate_send() {
+    use gen::generate_send;
+    use std::sync::mpsc;
+
+    fn assert_send<T: Send + 'static>(value: T) -> T {
+        value
+    }
+
+    let (tx, rx) = mpsc::channel::<Box<dyn Iterator<Item = String> + Send>>();
+    tx.send(Box::new(assert_send(generate_send(|co| async move {
+        co.yield_("job".to_string()).await;
+    }))))
+    .unwrap();
+    let job = rx.recv().unwrap();
+    let handle = std::thread::spawn(move || job.collect::<Vec<_>>());
+    assert_eq!(handle.join().unwrap(), ["job"]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn into_read() {
+    use gen::generate;
+    use std::io::Read;
+
+    let mut reader = generate(|co| async move {
+        co.yield_(vec![1, 2, 3]).await;
+        co.yield_(vec![]).await;
+        co.yield_(vec![4, 5]).await;
+    })
+    .into_read();
+
+    let mut buf = [0; 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [1, 2]);
+    assert_eq!(reader.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], 3);
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [4, 5]);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    assert!(reader.generator().is_finished());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn into_read_buf_read() {
+    use gen::generate;
+    use std::io::BufRead;
+
+    let mut reader = generate(|co| async move {
+        co.yield_(b"ab".to_vec()).await;
+        co.yield_(b"c\nd".to_vec()).await;
+    })
+    .into_read();
+
+    assert_eq!(reader.fill_buf().unwrap(), b"ab");
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().unwrap(), b"b");
+    reader.consume(1);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "c\n");
+    assert_eq!(reader.fill_buf().unwrap(), b"d");
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().unwrap(), b"");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn generate_from_write() {
+    use gen::generate_from_write;
+    use std::io::{self, Write};
+
+    let mut chunks = generate_from_write(1, |out| {
+        out.write_all(b"header")?;
+        out.write_all(b"")?;
+        Err(io::Error::other("encoder failed"))
+    });
+    assert_eq!(chunks.next().unwrap().unwrap(), b"header");
+    assert_eq!(
+        chunks.next().unwrap().unwrap_err().to_string(),
+        "encoder failed"
+    );
+    assert!(chunks.next().is_none());
+
+    // Dropping the iterator breaks the pipe
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut chunks = generate_from_write(0, move |out| {
+        let result = (0..).try_for_each(|_| out.write_all(b"row\n"));
+        tx.send(result.unwrap_err().kind()).unwrap();
+        Ok(())
+    });
+    assert_eq!(chunks.next().unwrap().unwrap(), b"row\n");
+    drop(chunks);
+    assert_eq!(rx.recv().unwrap(), io::ErrorKind::BrokenPipe);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn into_channel() {
+    use gen::generate;
+    use std::sync::mpsc;
+
+    let counter = |exited: mpsc::Sender<()>| {
+        generate(move |co| async move {
+            co.on_cancel(move || exited.send(()).unwrap());
+            for i in 0.. {
+                co.yield_(i).await;
+            }
+        })
+        .into_channel(0)
+    };
+
+    let (exited_tx, exited) = mpsc::channel();
+    let mut channel = counter(exited_tx);
+    assert_eq!(channel.next(), Some(0));
+    assert_eq!(channel.next(), Some(1));
+    assert_eq!(channel.join(), None);
+    assert_eq!(exited.try_recv(), Ok(()));
+
+    // Dropping mid-stream waits for the thread to exit
+    let (exited_tx, exited) = mpsc::channel();
+    let mut channel = counter(exited_tx);
+    assert_eq!(channel.next(), Some(0));
+    drop(channel);
+    assert_eq!(exited.try_recv(), Ok(()));
+    assert_eq!(exited.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_handle() {
+    use gen::{
+        ffi::{gen_bytes_free, gen_close, gen_free, gen_next, GenBytes, GenHandle},
+        generate,
+    };
+
+    let handle = GenHandle::new(generate(|co| async move {
+        co.yield_(vec![1, 2]).await;
+        co.yield_(vec![]).await;
+        co.yield_(vec![3]).await;
+    }));
+    let mut bytes = GenBytes {
+        data: std::ptr::null_mut(),
+        len: 0,
+    };
+    unsafe {
+        assert!(gen_next(handle, &mut bytes));
+        assert_eq!(std::slice::from_raw_parts(bytes.data, bytes.len), [1, 2]);
+        gen_bytes_free(bytes);
+        assert!(gen_next(handle, &mut bytes));
+        assert_eq!(bytes.len, 0);
+        gen_bytes_free(bytes);
+        gen_close(handle);
+        assert!(!gen_next(handle, &mut bytes));
+        gen_free(handle);
+        gen_free(std::ptr::null_mut());
+    }
+}
+
+#[test]
+fn unfold() {
+    use gen::unfold;
+
+    // Pages of a listing, each naming the next
+    let pages = [(vec![1, 2], Some(1)), (vec![3], Some(2)), (vec![], None)];
+    let items = unfold(Some(0), |page| {
+        let pages = &pages;
+        async move {
+            let (items, next) = pages[page?].clone();
+            Some((items, next))
+        }
+    });
+    assert_eq!(items.flatten().collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn from_fn_async() {
+    use gen::from_fn_async;
+    use std::cell::Cell;
+
+    let calls = &Cell::new(0);
+    let mut readings = from_fn_async(|| async move {
+        calls.set(calls.get() + 1);
+        (calls.get() <= 3).then(|| calls.get() * 10)
+    });
+    assert_eq!(readings.next(), Some(10));
+    assert_eq!(calls.get(), 1);
+    assert_eq!(readings.by_ref().collect::<Vec<_>>(), [20, 30]);
+    assert_eq!(calls.get(), 4);
+}
+
+#[test]
+fn successors_async() {
+    use gen::successors_async;
+
+    let powers = successors_async(Some(1u8), |&n| async move { n.checked_mul(4) });
+    assert_eq!(powers.collect::<Vec<_>>(), [1, 4, 16, 64]);
+    assert_eq!(
+        successors_async(None, |&n: &u8| async move { Some(n) }).count(),
+        0
+    );
+}
+
+#[test]
+fn once_future() {
+    use gen::{merge, once_future};
+
+    let mut once = once_future(async { "ready" });
+    assert_eq!(once.size_hint(), (0, None));
+    assert_eq!(once.next(), Some("ready"));
+    assert_eq!(once.next(), None);
+
+    // One-shot computations alongside other generators
+    let merged = merge((1..=2).map(|i| once_future(async move { i })));
+    assert_eq!(merged.collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+fn yield_with() {
+    use gen::generate_bidi;
+    use std::cell::Cell;
+
+    let built = &Cell::new(0);
+    let mut generator = generate_bidi(|co| async move {
+        let mut resumes = Vec::new();
+        for i in 0..3 {
+            resumes.push(
+                co.yield_with(|| {
+                    built.set(built.get() + 1);
+                    i
+                })
+                .await,
+            );
+        }
+        resumes
+    });
+
+    assert_eq!(generator.next(), Some(0));
+    assert_eq!(generator.send("a"), Some(1));
+    generator.close();
+    assert_eq!(built.get(), 2);
+    assert_eq!(
+        generator.return_value(),
+        Some(&vec![Some("a"), Some(""), None])
+    );
+}
+
+#[test]
+fn yield_chunk() {
+    use gen::generate;
+
+    let mut generator = generate(|co| async move {
+        co.yield_chunk(vec![1, 2, 3]).await;
+        co.yield_chunk(Vec::new()).await;
+        co.yield_(4).await;
+        co.yield_chunk([5, 6]).await;
+    });
+
+    assert_eq!(generator.size_hint().0, 0);
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.size_hint().0, 2);
+    assert_eq!(generator.by_ref().take(3).collect::<Vec<_>>(), [2, 3, 4]);
+    assert_eq!(generator.polls(), 2);
+    assert_eq!(generator.next(), Some(5));
+    generator.close();
+    assert!(generator.is_finished());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn try_next_errors() {
+    use gen::{generate, Error};
+
+    let mut generator = generate(|co| async move {
+        co.yield_(1).await;
+        panic!("bad record");
+    });
+    generator.set_catch_panics(true);
+    assert_eq!(generator.try_next(), Ok(Some(1)));
+    assert_eq!(generator.try_next(), Err(Error::Panicked));
+    assert_eq!(generator.try_next(), Err(Error::Poisoned));
+
+    let mut generator = generate(|co| async move {
+        let mut yield_ = co.yield_(1);
+        (&mut yield_).await;
+        // Polled again after it resolved
+        (&mut yield_).await;
+    });
+    assert_eq!(generator.try_next(), Ok(Some(1)));
+    assert_eq!(generator.try_next(), Err(Error::WrongExecutor));
+    assert_eq!(generator.try_next(), Err(Error::Poisoned));
+
+    let mut generator = generate(|co| async move {
+        co.yield_(1).await;
+        "done"
+    });
+    assert_eq!(generator.try_next(), Ok(Some(1)));
+    assert_eq!(generator.try_next(), Ok(None));
+    assert_eq!(generator.try_next(), Ok(None));
+    assert_eq!(generator.return_value(), Some(&"done"));
+}
+
+#[test]
+fn into_sync_iter() {
+    use gen::{Communication, IntoSyncIterator};
+
+    async fn words(co: Communication<&'static str>, text: &'static str) -> usize {
+        let mut count = 0;
+        for word in text.split(' ') {
+            co.yield_(word).await;
+            count += 1;
+        }
+        count
+    }
+
+    let mut iter = (|co| words(co, "a b c")).into_sync_iter();
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), ["a", "b", "c"]);
+    assert_eq!(iter.return_value(), Some(&3));
+}
+
+#[test]
+fn map_yield() {
+    use gen::{generate_bidi, Communication};
+
+    // Doubles whatever it's sent, as strings
+    async fn echo(co: Communication<String, i32>) -> i32 {
+        let mut last = co.yield_("start".to_string()).await;
+        while !co.is_closed() {
+            last = co.yield_((last * 2).to_string()).await;
+        }
+        last
+    }
+
+    let mut generator =
+        generate_bidi(|co| async move { co.map_yield(|s: String| s.len(), echo).await });
+    assert_eq!(generator.next(), Some(5));
+    assert_eq!(generator.send(50), Some(3));
+    assert_eq!(generator.send(5), Some(2));
+    generator.close();
+    assert_eq!(generator.return_value(), Some(&0));
+}
+
+#[test]
+fn with_converter() {
+    use gen::{generate, Communication};
+
+    #[derive(Debug, PartialEq)]
+    enum Token {
+        Word(&'static str),
+        Number(i64),
+    }
+    impl From<&'static str> for Token {
+        fn from(word: &'static str) -> Self {
+            Token::Word(word)
+        }
+    }
+
+    let iter = generate(|co: Communication<Token>| async move {
+        co.yield_(Token::Number(1)).await;
+        co.with_converter::<&'static str, _, _>(|co| async move {
+            co.yield_all(["a", "b"]).await;
+        })
+        .await;
+    });
+    assert_eq!(
+        iter.collect::<Vec<_>>(),
+        [Token::Number(1), Token::Word("a"), Token::Word("b")]
+    );
+}
+
+#[test]
+fn async_next() {
+    use gen::generate;
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Waker},
+    };
+
+    let mut producer = generate(|co| async move {
+        co.yield_("a").await;
+        co.yield_now().await;
+        co.yield_("b").await;
+        "done"
+    });
+    let mut items = Vec::new();
+    {
+        let mut consumer = pin!(async {
+            while let Some(item) = producer.async_next().await {
+                items.push(item);
+            }
+        });
+        let mut cx = Context::from_waker(Waker::noop());
+        while consumer.as_mut().poll(&mut cx).is_pending() {}
+    }
+    assert_eq!(items, ["a", "b"]);
+    assert_eq!(producer.return_value(), Some(&"done"));
+}
+
+#[test]
+fn map_items_and_return() {
+    use gen::{generate, generate_buffered};
+
+    let mut doubled = generate(|co| async move {
+        co.yield_all([1, 2, 3]).await;
+        "sum"
+    })
+    .map_items(|n| n * 2);
+    assert_eq!(doubled.next(), Some(2));
+    doubled.generator_mut().close();
+    assert_eq!(doubled.next(), None);
+    assert!(doubled.generator().is_finished());
+
+    // A return value which arrived before map_return gets mapped too
+    let mut generator = generate_buffered(4, |co| async move {
+        co.yield_all([1, 2]).await;
+        10
+    });
+    assert_eq!(generator.next(), Some(1));
+    let mut generator = generator.map_return(|n| n + 1);
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [2]);
+    assert_eq!(generator.into_return(), Some(11));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn gen_builder() {
+    use gen::{CancellationToken, DropPolicy, GenBuilder};
+    use std::cell::Cell;
+
+    let token = CancellationToken::new();
+    let finished = &Cell::new(false);
+    let mut generator = GenBuilder::new()
+        .catch_panics(true)
+        .drop_policy(DropPolicy::Complete)
+        .cancellation_token(token.clone())
+        .spawn(|co| async move {
+            while !co.is_cancelled() {
+                co.yield_(()).await;
+            }
+            finished.set(true);
+        });
+
+    assert_eq!(generator.next(), Some(()));
+    token.cancel();
+    drop(generator);
+    assert!(finished.get());
+
+    let mut generator = GenBuilder::new().catch_panics(true).spawn(|co| async move {
+        co.yield_(1).await;
+        panic!("contained");
+    });
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [1]);
+    assert!(generator.panic_payload().is_some());
+}
+
+#[test]
+fn ticker() {
+    use gen::tick;
+    use std::cell::Cell;
+
+    let step = &Cell::new(0);
+    let mut ticker = tick(|t| async move {
+        step.set(1);
+        t.tick().await;
+        step.set(2);
+        t.tick().await;
+        step.set(3);
+    });
+
+    assert_eq!(step.get(), 0);
+    assert_eq!(ticker.next(), Some(()));
+    assert_eq!(step.get(), 1);
+    assert_eq!(ticker.next(), Some(()));
+    assert_eq!(step.get(), 2);
+    assert!(!ticker.is_finished());
+    assert_eq!(ticker.next(), None);
+    assert_eq!(step.get(), 3);
+    assert!(ticker.is_finished());
+    assert_eq!(ticker.next(), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn ticker_panic_finishes() {
+    use gen::tick;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut ticker = tick(|t| async move {
+        t.tick().await;
+        panic!("bad frame");
+    });
+
+    assert_eq!(ticker.next(), Some(()));
+    assert!(catch_unwind(AssertUnwindSafe(|| ticker.next())).is_err());
+    assert!(ticker.is_finished());
+    assert_eq!(ticker.next(), None);
+}
+
+#[test]
+fn progress() {
+    use gen::{generate, Progress};
+
+    let mut generator = generate(|co| async move {
+        co.progress(0u64);
+        for chunk in [10, 20, 30] {
+            co.yield_(chunk).await;
+            co.progress(chunk as u64);
+        }
+        co.progress(1.0);
+    });
+
+    assert_eq!(generator.progress(), None);
+    assert_eq!(generator.next(), Some(10));
+    assert_eq!(generator.progress(), Some(Progress::Count(0)));
+    assert_eq!(generator.next(), Some(20));
+    assert_eq!(generator.progress(), Some(Progress::Count(10)));
+    generator.by_ref().for_each(drop);
+    assert_eq!(generator.progress(), Some(Progress::Fraction(1.0)));
+}
+
+#[test]
+fn annotate() {
+    use gen::generate;
+
+    let mut generator = generate(|co| async move {
+        co.annotate("schema", "v1");
+        co.yield_(1).await;
+        co.annotate("schema", String::from("v2"));
+        co.annotate("warning", format!("{} bad rows", 3));
+    });
+
+    assert!(generator.metadata().is_empty());
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.metadata()["schema"], "v1");
+    assert_eq!(generator.next(), None);
+    let metadata = generator.metadata();
+    assert_eq!(metadata["schema"], "v2");
+    assert_eq!(metadata["warning"], "3 bad rows");
+}
+
+#[test]
+fn unconsumed_items() {
+    use gen::{generate, generate_buffered, DropPolicy};
+    use std::{
+        future::{poll_fn, Future},
+        sync::{Arc, Mutex},
+        task::Poll,
+    };
+
+    let mut generator = generate_buffered(3, |co| async move {
+        co.yield_all(1..=5).await;
+    });
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.take_pending(), [2, 3]);
+    assert_eq!(generator.by_ref().collect::<Vec<_>>(), [4, 5]);
+
+    // Items from several clones of co, each waiting on its yield_
+    let mut generator = generate(|co| async move {
+        let mut yields = [1, 2, 3].map(|i| {
+            let co = co.clone();
+            Some(Box::pin(async move { co.yield_(i).await }))
+        });
+        poll_fn(|cx| {
+            for slot in &mut yields {
+                if slot
+                    .as_mut()
+                    .is_some_and(|y| y.as_mut().poll(cx).is_ready())
+                {
+                    *slot = None;
+                }
+            }
+            match yields.iter().all(Option::is_none) {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await;
+        co.yield_(4).await;
+    });
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.take_pending(), [2, 3]);
+    assert_eq!(generator.try_next(), Ok(Some(4)));
+    assert_eq!(generator.try_next(), Ok(None));
+
+    let released = Arc::new(Mutex::new(Vec::new()));
+    let mut generator = generate(|co| async move {
+        co.yield_chunk([1, 2, 3]).await;
+        co.yield_(4).await;
+    });
+    let sink = released.clone();
+    generator.set_on_unconsumed(move |item| sink.lock().unwrap().push(item));
+    generator.set_drop_policy(DropPolicy::Complete);
+    assert_eq!(generator.next(), Some(1));
+    drop(generator);
+    assert_eq!(*released.lock().unwrap(), [2, 3, 4]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn unconsumed_items_on_close() {
+    use gen::{generate, generate_buffered, DropPolicy};
+    use std::sync::{Arc, Mutex};
+
+    let released = Arc::new(Mutex::new(Vec::new()));
+    let sink = released.clone();
+    let mut generator = generate(|co| async move {
+        co.yield_(1).await;
+        co.yield_(2).await;
+    });
+    generator.set_on_unconsumed(move |item| sink.lock().unwrap().push(item));
+    generator.set_drop_policy(DropPolicy::Close);
+    assert_eq!(generator.next(), Some(1));
+    drop(generator);
+    assert_eq!(*released.lock().unwrap(), [2]);
+
+    // Queued items, then what the body yields while finishing
+    let released = Arc::new(Mutex::new(Vec::new()));
+    let sink = released.clone();
+    let mut generator = generate_buffered(4, |co| async move {
+        let mut i = 0;
+        while !co.is_closed() {
+            co.yield_(i).await;
+            i += 1;
+        }
+        co.yield_all([-1, -2]).await;
+        i
+    });
+    generator.set_on_unconsumed(move |item| sink.lock().unwrap().push(item));
+    assert_eq!(generator.next(), Some(0));
+    generator.close();
+    assert_eq!(*released.lock().unwrap(), [1, 2, 3, -1, -2]);
+    assert_eq!(generator.into_return(), Some(4));
+
+    let released = Arc::new(Mutex::new(Vec::new()));
+    let sink = released.clone();
+    let mut generator = generate_buffered(4, |co| async move {
+        co.yield_all([1, 2, 3]).await;
+        panic!("bad record");
+    });
+    generator.set_catch_panics(true);
+    generator.set_on_unconsumed(move |item| sink.lock().unwrap().push(item));
+    assert_eq!(generator.next(), None);
+    assert_eq!(*released.lock().unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn communication_pair() {
+    use gen::Communication;
+
+    struct Tokenizer<'a> {
+        text: &'a str,
+    }
+    impl<'a> Tokenizer<'a> {
+        async fn run(&self, co: Communication<&'a str, bool>) -> usize {
+            let mut skipped = 0;
+            for word in self.text.split_whitespace() {
+                if !co.yield_(word).await {
+                    skipped += 1;
+                }
+            }
+            skipped
+        }
+    }
+
+    let tokenizer = Tokenizer { text: "a b c" };
+    let (co, attach) = Communication::pair();
+    let mut generator = attach.into_generator(tokenizer.run(co));
+    assert_eq!(generator.send(true), Some("a"));
+    assert_eq!(generator.send(false), Some("b"));
+    assert_eq!(generator.send(true), Some("c"));
+    assert_eq!(generator.send(false), None);
+    assert_eq!(generator.return_value(), Some(&2));
+}
+
+#[test]
+fn generator_from_future() {
+    use gen::{Communication, Generator};
+    use std::collections::HashMap;
+
+    // Futures built ahead of time and picked by name
+    let mut jobs = HashMap::new();
+    for name in ["a", "b"] {
+        let (co, _) = Communication::<String>::pair();
+        let future = {
+            let co = co.clone();
+            async move {
+                co.yield_(name.repeat(2)).await;
+            }
+        };
+        jobs.insert(name, Generator::from_future(co, future));
+    }
+    let b = jobs.remove("b").unwrap();
+    assert_eq!(b.collect::<Vec<_>>(), ["bb"]);
+    assert!(!jobs["a"].is_finished());
+}
+
+#[test]
+fn generate_unpin() {
+    use gen::generate_unpin;
+    use std::future::{ready, Ready};
+
+    // Finishes without yielding; nothing is boxed
+    let mut generator = generate_unpin::<(), _, Ready<&str>>(|_| ready("done"));
+    assert_eq!(generator.next(), None);
+    assert_eq!(generator.return_value(), Some(&"done"));
+
+    // Boxed futures are Unpin too
+    let mut generator = generate_unpin(|co| {
+        Box::pin(async move {
+            co.yield_(1).await;
+        })
+    });
+    assert_eq!(generator.next(), Some(1));
+    assert_eq!(generator.next(), None);
+}